@@ -0,0 +1,59 @@
+// Checks signature_vectors.json against the JS implementations the network uses.
+//
+//   npm install @shardus/crypto-utils ethers
+//   node fixtures/verify_signature_vectors.js          # report mismatches
+//   node fixtures/verify_signature_vectors.js --write  # record the JS results as the expected values
+//
+// With --write the corpus is marked as verified against the installed versions.
+// A vector the Rust builders disagree with then fails `cargo test signature_vectors`.
+const fs = require('fs')
+const path = require('path')
+const crypto = require('@shardus/crypto-utils')
+const { Wallet } = require('ethers')
+
+const file = path.join(__dirname, 'signature_vectors.json')
+const corpus = JSON.parse(fs.readFileSync(file, 'utf8'))
+const write = process.argv.includes('--write')
+const stringify = crypto.safeStringify || crypto.stringify
+
+crypto.init(corpus.hash_key)
+
+async function main() {
+  let mismatches = 0
+  for (const vector of corpus.vectors) {
+    const payload = stringify(vector.tx)
+    const hash = crypto.hash(payload)
+    const wallet = new Wallet(vector.private_key)
+    const sign = {
+      owner: wallet.address.toLowerCase().slice(2).padEnd(64, '0'),
+      sig: (await wallet.signMessage(hash)).toLowerCase(),
+    }
+
+    const diffs = []
+    if (payload !== vector.payload) diffs.push(`payload\n    js:   ${payload}\n    rust: ${vector.payload}`)
+    if (hash !== vector.hash) diffs.push(`hash js ${hash} rust ${vector.hash}`)
+    if (sign.owner !== vector.sign.owner) diffs.push(`owner js ${sign.owner} rust ${vector.sign.owner}`)
+    if (sign.sig !== vector.sign.sig) diffs.push(`sig js ${sign.sig} rust ${vector.sign.sig}`)
+
+    if (diffs.length) {
+      mismatches++
+      console.log(`MISMATCH ${vector.name}\n  ${diffs.join('\n  ')}`)
+    } else {
+      console.log(`ok       ${vector.name}`)
+    }
+    Object.assign(vector, write ? { payload, hash, sign } : {})
+  }
+
+  if (write) {
+    corpus.provenance = {
+      source: 'js',
+      verified_against_js: true,
+      note: `Expected values produced by @shardus/crypto-utils ${require('@shardus/crypto-utils/package.json').version} and ethers ${require('ethers/package.json').version}.`,
+    }
+    fs.writeFileSync(file, JSON.stringify(corpus, null, 2) + '\n')
+    console.log(`wrote ${file}`)
+  }
+  process.exit(mismatches && !write ? 1 : 0)
+}
+
+main()
@@ -0,0 +1,126 @@
+"""Seals the xmessage vector in xmessage_vector.json with an implementation independent of the crate.
+
+    pip install cryptography   # 44 or later, built against OpenSSL 3.5 or later for ML-KEM
+    python3 fixtures/xmessage_vector.py
+
+ML-KEM-1024 and secp256k1 ECDH come from OpenSSL through `cryptography`, BLAKE2b from hashlib.
+XChaCha20-Poly1305 is HChaCha20 below in front of OpenSSL's ChaCha20-Poly1305.
+`cargo test xmessage_vector` opens the result with the crate's implementation.
+"""
+import base64
+import hashlib
+import json
+import os
+import struct
+
+import cryptography
+from cryptography.hazmat.primitives import serialization
+from cryptography.hazmat.primitives.asymmetric import ec, mlkem
+from cryptography.hazmat.primitives.ciphers.aead import ChaCha20Poly1305
+
+HASH_KEY = "69fa4195670576c0160d660c3be36556ff8d504725be8a59b5a96509e0c994bc"
+PQ_SEED_DOMAIN = b"load-orchestra pq seed"
+
+# the well-known Hardhat development keys 0 and 1
+SENDER_KEY = "ac0974bec39a17e36ba4a6b4d238ff944bacb478cbed5efcae784d7bf4f2ff80"
+RECIPIENT_KEY = "59c6995e998f97a5a0044966f0945389dc9e86dae88c7a8412f4603b6b78690d"
+SENDER_ADDRESS = "f39fd6e51aad88f6f4ce6ab8827279cfffb92266"
+MESSAGE = 'hello "liberdus" ☃\n'
+SENT_TIMESTAMP = 1700000000000
+
+
+def hchacha20(key, nonce16):
+    def rotl(v, n):
+        return ((v << n) & 0xFFFFFFFF) | (v >> (32 - n))
+
+    def quarter_round(s, a, b, c, d):
+        s[a] = (s[a] + s[b]) & 0xFFFFFFFF
+        s[d] = rotl(s[d] ^ s[a], 16)
+        s[c] = (s[c] + s[d]) & 0xFFFFFFFF
+        s[b] = rotl(s[b] ^ s[c], 12)
+        s[a] = (s[a] + s[b]) & 0xFFFFFFFF
+        s[d] = rotl(s[d] ^ s[a], 8)
+        s[c] = (s[c] + s[d]) & 0xFFFFFFFF
+        s[b] = rotl(s[b] ^ s[c], 7)
+
+    s = list(struct.unpack("<4I", b"expand 32-byte k"))
+    s += list(struct.unpack("<8I", key)) + list(struct.unpack("<4I", nonce16))
+    for _ in range(10):
+        quarter_round(s, 0, 4, 8, 12)
+        quarter_round(s, 1, 5, 9, 13)
+        quarter_round(s, 2, 6, 10, 14)
+        quarter_round(s, 3, 7, 11, 15)
+        quarter_round(s, 0, 5, 10, 15)
+        quarter_round(s, 1, 6, 11, 12)
+        quarter_round(s, 2, 7, 8, 13)
+        quarter_round(s, 3, 4, 9, 14)
+    return struct.pack("<8I", *(s[0:4] + s[12:16]))
+
+
+def encrypt(key, plaintext):
+    """base64 of a random 24-byte nonce and the XChaCha20-Poly1305 ciphertext"""
+    nonce = os.urandom(24)
+    subkey = hchacha20(key, nonce[:16])
+    sealed = ChaCha20Poly1305(subkey).encrypt(b"\0" * 4 + nonce[16:], plaintext, None)
+    return base64.b64encode(nonce + sealed).decode()
+
+
+def secp256k1(private_key_hex):
+    return ec.derive_private_key(int(private_key_hex, 16), ec.SECP256K1())
+
+
+def main():
+    sender = secp256k1(SENDER_KEY)
+    recipient = secp256k1(RECIPIENT_KEY)
+
+    pq_seed = hashlib.sha3_512(PQ_SEED_DOMAIN + bytes.fromhex(RECIPIENT_KEY)).digest()
+    pq_public_key = (
+        mlkem.MLKEM1024PrivateKey.from_seed_bytes(pq_seed)
+        .public_key()
+        .public_bytes(serialization.Encoding.Raw, serialization.PublicFormat.Raw)
+    )
+    pq_shared, pq_ciphertext = mlkem.MLKEM1024PublicKey.from_public_bytes(
+        pq_public_key
+    ).encapsulate()
+
+    # OpenSSL's ECDH output is the x coordinate of the shared point
+    ec_shared = sender.exchange(ec.ECDH(), recipient.public_key())
+    key = hashlib.blake2b(
+        ec_shared + pq_shared, digest_size=32, key=bytes.fromhex(HASH_KEY)
+    ).digest()
+    sender_info = json.dumps(
+        {"username": "load" + SENDER_ADDRESS[:12]}, separators=(",", ":")
+    )
+
+    vector = {
+        "provenance": {
+            "source": "python",
+            "produced_by_client": False,
+            "note": "Sealed by fixtures/xmessage_vector.py with cryptography %s (%s). "
+            "An independent implementation of the client's scheme, not output of the Liberdus client."
+            % (cryptography.__version__, cryptography.hazmat.backends.openssl.backend.openssl_version_text()),
+        },
+        "sender_private_key": "0x" + SENDER_KEY,
+        "recipient_private_key": "0x" + RECIPIENT_KEY,
+        "recipient_pq_public_key": base64.b64encode(pq_public_key).decode(),
+        "message_key": key.hex(),
+        "message": MESSAGE,
+        "sender_info": json.loads(sender_info),
+        "xmessage": {
+            "encrypted": True,
+            "encryptionMethod": "xchacha20poly1305",
+            "message": encrypt(key, MESSAGE.encode()),
+            "pqSharedKey": base64.b64encode(pq_ciphertext).decode(),
+            "senderInfo": encrypt(key, sender_info.encode()),
+            "sent_timestamp": SENT_TIMESTAMP,
+        },
+    }
+    path = os.path.join(os.path.dirname(os.path.abspath(__file__)), "xmessage_vector.json")
+    with open(path, "w") as f:
+        json.dump(vector, f, indent=2, ensure_ascii=False)
+        f.write("\n")
+    print("wrote", path)
+
+
+if __name__ == "__main__":
+    main()
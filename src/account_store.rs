@@ -0,0 +1,1030 @@
+use crate::{cli::verbose, confirmation, keystore, transactions};
+use alloy::signers::local::PrivateKeySigner;
+use fs2::FileExt;
+use serde::{Deserialize, Serialize};
+use std::io::Write;
+use std::sync::{Arc, OnceLock};
+use tokio::time::Duration;
+
+pub const ACCOUNTS_FILE: &str = "./artifacts/registered_accounts.json";
+const LOCK_FILE: &str = "./artifacts/registered_accounts.json.lock";
+
+/// How long accounts checked out by a run stay leased if it never releases them
+pub const DEFAULT_LEASE_TIMEOUT: Duration = Duration::from_secs(3600);
+
+static LEASE_TIMEOUT: OnceLock<Duration> = OnceLock::new();
+static RUN_ID: OnceLock<String> = OnceLock::new();
+
+/// Stored accounts looked up at once when checking them against the network
+const MAX_CONCURRENT_ACCOUNT_CHECKS: usize = 32;
+
+/// The network an account was registered on, accounts are only reused on the
+/// network and gateway they came from
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct NetworkKey {
+    pub network_id: String,
+    pub gateway_url: String,
+}
+
+impl NetworkKey {
+    pub fn new(network_id: &str, gateway_url: &str) -> Self {
+        NetworkKey {
+            network_id: network_id.to_string(),
+            gateway_url: gateway_url.trim_end_matches('/').to_string(),
+        }
+    }
+
+    /// Key of the network this run talks to
+    pub fn current(gateway_url: &str) -> Self {
+        Self::new(transactions::get_network_id(), gateway_url)
+    }
+}
+
+impl std::fmt::Display for NetworkKey {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "{} via {}", self.network_id, self.gateway_url)
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct StoredAccount {
+    pub private_key: String,
+    pub address: String,
+    pub alias: String,
+    pub registration_tx_id: Option<String>,
+    pub registered_at: u128,
+    /// Set when the account was missing on the network or out of funds on reuse,
+    /// stale accounts are not loaded again
+    #[serde(default)]
+    pub stale: bool,
+    /// `None` for accounts saved before stores were keyed by network
+    #[serde(default)]
+    pub network_id: Option<String>,
+    #[serde(default)]
+    pub gateway_url: Option<String>,
+    /// Balance in wei as of `balance_updated_at`, set by `accounts refresh` and `accounts prune`
+    #[serde(default)]
+    pub balance: Option<u128>,
+    #[serde(default)]
+    pub balance_updated_at: Option<u128>,
+    /// Set while a run has the account checked out
+    #[serde(default)]
+    pub lease: Option<Lease>,
+}
+
+/// A run's claim on an account, other runs skip the account until it is
+/// released or `expires_at` (unix ms) passes
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct Lease {
+    pub holder: String,
+    pub expires_at: u128,
+}
+
+impl Lease {
+    pub fn is_active(&self, now: u128) -> bool {
+        self.expires_at > now
+    }
+}
+
+/// Set how long this run's leases last, like the network id it can only be set once
+pub fn set_lease_timeout(timeout: Duration) -> Result<(), String> {
+    LEASE_TIMEOUT
+        .set(timeout)
+        .map_err(|_| "lease timeout is already set".to_string())
+}
+
+/// Identifies the leases of this process
+pub fn run_id() -> &'static str {
+    RUN_ID.get_or_init(|| format!("{}-{}", std::process::id(), now_millis()))
+}
+
+fn run_lease(now: u128) -> Lease {
+    let timeout = LEASE_TIMEOUT.get().unwrap_or(&DEFAULT_LEASE_TIMEOUT);
+    Lease {
+        holder: run_id().to_string(),
+        expires_at: now + timeout.as_millis(),
+    }
+}
+
+impl StoredAccount {
+    /// Whether the account may be reused on `network`. Legacy accounts without
+    /// a network may be, they are claimed by the first network they validate on.
+    pub fn belongs_to(&self, network: &NetworkKey) -> bool {
+        match (&self.network_id, &self.gateway_url) {
+            (None, None) => true,
+            (Some(network_id), Some(gateway_url)) => {
+                *network_id == network.network_id
+                    && gateway_url.trim_end_matches('/') == network.gateway_url
+            }
+            _ => false,
+        }
+    }
+
+    pub fn is_legacy(&self) -> bool {
+        self.network_id.is_none() && self.gateway_url.is_none()
+    }
+
+    /// Not stale and not leased to a run
+    pub fn is_available(&self, now: u128) -> bool {
+        !self.stale && !self.lease.as_ref().is_some_and(|l| l.is_active(now))
+    }
+
+    pub fn signer(&self) -> Result<PrivateKeySigner, Box<dyn std::error::Error>> {
+        let private_key_bytes = hex::decode(&self.private_key)?;
+        if private_key_bytes.len() != 32 {
+            return Err("Invalid private key length".into());
+        }
+        let mut key_array = [0u8; 32];
+        key_array.copy_from_slice(&private_key_bytes);
+        Ok(PrivateKeySigner::from_bytes(&key_array.into())?)
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct AccountsStorage {
+    pub accounts: Vec<StoredAccount>,
+    pub last_updated: u128,
+}
+
+fn now_millis() -> u128 {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .unwrap()
+        .as_millis()
+}
+
+/// Save registered accounts of `network` to JSON file. Accounts a run registers
+/// are `leased` to it, imported ones are left free for the next run to check out.
+pub async fn save_accounts_to_file(
+    accounts: &[(PrivateKeySigner, String, Option<String>)], // (signer, alias, tx_id)
+    network: &NetworkKey,
+    leased: bool,
+    verbosity: &bool,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let now = now_millis();
+    let lease = leased.then(|| run_lease(now));
+    let total = update_storage(|storage| add_accounts(storage, accounts, network, lease, now))?;
+
+    verbose(
+        verbosity,
+        &format!("Saved {} accounts to {}", total, ACCOUNTS_FILE),
+    );
+
+    Ok(())
+}
+
+/// Add the accounts not yet in `storage`, under `lease` if any, returns the new total
+fn add_accounts(
+    storage: &mut AccountsStorage,
+    accounts: &[(PrivateKeySigner, String, Option<String>)],
+    network: &NetworkKey,
+    lease: Option<Lease>,
+    now: u128,
+) -> usize {
+    for (signer, alias, tx_id) in accounts {
+        let address = signer.address().to_string();
+        // avoid duplicates by address
+        if storage.accounts.iter().any(|acc| acc.address == address) {
+            continue;
+        }
+        storage.accounts.push(StoredAccount {
+            private_key: hex::encode(signer.to_bytes()),
+            address,
+            alias: alias.clone(),
+            registration_tx_id: tx_id.clone(),
+            registered_at: now,
+            stale: false,
+            network_id: Some(network.network_id.clone()),
+            gateway_url: Some(network.gateway_url.clone()),
+            balance: None,
+            balance_updated_at: None,
+            lease: lease.clone(),
+        });
+    }
+    storage.accounts.len()
+}
+
+/// Check out up to `max_accounts` usable accounts of `network` from JSON file.
+/// They are leased to this run, so concurrent runs get disjoint accounts, until
+/// `release_leases` or the lease timeout. Accounts known to be registered on
+/// `network` come before legacy ones.
+pub async fn load_accounts_from_file(
+    network: &NetworkKey,
+    max_accounts: usize,
+    verbosity: &bool,
+) -> Result<Vec<PrivateKeySigner>, Box<dyn std::error::Error>> {
+    if !std::path::Path::new(ACCOUNTS_FILE).exists() {
+        return Err(format!("{} does not exist", ACCOUNTS_FILE).into());
+    }
+    let now = now_millis();
+    let leased = update_storage(|storage| {
+        checkout_accounts(storage, network, max_accounts, &run_lease(now), now)
+    })?;
+
+    let mut signers = Vec::new();
+    for stored_account in &leased {
+        signers.push(stored_account.signer()?);
+    }
+
+    verbose(
+        verbosity,
+        &format!(
+            "Leased {} accounts of {} from {}",
+            signers.len(),
+            network,
+            ACCOUNTS_FILE
+        ),
+    );
+
+    Ok(signers)
+}
+
+/// Lease up to `max_accounts` available accounts of `network` in `storage`,
+/// returns copies of the leased accounts
+fn checkout_accounts(
+    storage: &mut AccountsStorage,
+    network: &NetworkKey,
+    max_accounts: usize,
+    lease: &Lease,
+    now: u128,
+) -> Vec<StoredAccount> {
+    let mut candidates: Vec<&mut StoredAccount> = storage
+        .accounts
+        .iter_mut()
+        .filter(|account| account.is_available(now) && account.belongs_to(network))
+        .collect();
+    candidates.sort_by_key(|account| account.is_legacy());
+
+    candidates
+        .into_iter()
+        .take(max_accounts)
+        .map(|account| {
+            account.lease = Some(lease.clone());
+            account.clone()
+        })
+        .collect()
+}
+
+/// Give back every account leased by this run, called when the run ends
+pub fn release_leases() -> Result<usize, Box<dyn std::error::Error>> {
+    // the run id is only made up once something is leased
+    if RUN_ID.get().is_none() || !std::path::Path::new(ACCOUNTS_FILE).exists() {
+        return Ok(0);
+    }
+    update_storage(|storage| {
+        let mut released = 0;
+        for account in storage.accounts.iter_mut() {
+            if account.lease.as_ref().is_some_and(|l| l.holder == run_id()) {
+                account.lease = None;
+                released += 1;
+            }
+        }
+        released
+    })
+}
+
+/// Flag accounts in the store as stale so later runs stop loading them.
+/// Legacy accounts are left alone, they may still be good on another network.
+pub fn mark_stale_accounts(addresses: &[String]) -> Result<(), Box<dyn std::error::Error>> {
+    update_storage(|storage| {
+        for account in storage.accounts.iter_mut() {
+            if !account.is_legacy() && addresses.contains(&account.address) {
+                account.stale = true;
+            }
+        }
+    })
+}
+
+/// Stamp legacy accounts that turned out to live on `network` with its key
+pub fn claim_legacy_accounts(
+    addresses: &[String],
+    network: &NetworkKey,
+) -> Result<usize, Box<dyn std::error::Error>> {
+    update_storage(|storage| {
+        let mut claimed = 0;
+        for account in storage.accounts.iter_mut() {
+            if account.is_legacy() && addresses.contains(&account.address) {
+                account.network_id = Some(network.network_id.clone());
+                account.gateway_url = Some(network.gateway_url.clone());
+                claimed += 1;
+            }
+        }
+        claimed
+    })
+}
+
+/// What the network says about a stored account
+#[derive(Debug, Clone, PartialEq)]
+pub enum OnChainStatus {
+    Found { balance: u128 },
+    Missing,
+    /// The lookup itself failed, nothing is known about the account
+    Unreachable(String),
+}
+
+/// Look every account up on the gateway, a bounded number at a time
+pub async fn check_accounts(
+    wallets: Vec<PrivateKeySigner>,
+    gateway_url: &str,
+) -> Vec<(PrivateKeySigner, OnChainStatus)> {
+    // uses ARC internally
+    let http_client = reqwest::Client::builder()
+        .danger_accept_invalid_certs(true)
+        .build()
+        .unwrap();
+    let checks = Arc::new(tokio::sync::Semaphore::new(MAX_CONCURRENT_ACCOUNT_CHECKS));
+
+    let mut lookups = tokio::task::JoinSet::new();
+    for wallet in wallets {
+        let http_client = http_client.clone();
+        let checks = Arc::clone(&checks);
+        let gateway_url = gateway_url.to_string();
+        lookups.spawn(async move {
+            let _permit = checks.acquire().await.expect("check semaphore closed");
+            let status =
+                match confirmation::fetch_account(&http_client, &gateway_url, &wallet.address())
+                    .await
+                {
+                    Ok(Some(account)) => OnChainStatus::Found {
+                        balance: confirmation::account_balance(&account).unwrap_or_default(),
+                    },
+                    Ok(None) => OnChainStatus::Missing,
+                    Err(e) => OnChainStatus::Unreachable(e),
+                };
+            (wallet, status)
+        });
+    }
+
+    let mut results = Vec::new();
+    while let Some(lookup) = lookups.join_next().await {
+        results.push(lookup.expect("account check panicked"));
+    }
+    results
+}
+
+/// Which stored accounts an `accounts` command looks at
+pub enum AccountScope {
+    /// Accounts registered on this network, legacy ones included
+    Network(NetworkKey),
+    All,
+}
+
+impl AccountScope {
+    fn contains(&self, account: &StoredAccount) -> bool {
+        match self {
+            AccountScope::Network(network) => account.belongs_to(network),
+            AccountScope::All => true,
+        }
+    }
+}
+
+fn network_label(account: &StoredAccount) -> String {
+    match (&account.network_id, &account.gateway_url) {
+        (Some(network_id), Some(gateway_url)) => format!("{} via {}", network_id, gateway_url),
+        _ => "legacy".to_string(),
+    }
+}
+
+/// Print the stored accounts in `scope`, one per line
+pub fn list_accounts(scope: &AccountScope) -> Result<(), Box<dyn std::error::Error>> {
+    let storage = load_accounts_from_file_internal()?;
+    let now = now_millis();
+    println!(
+        "{:<44} {:<24} {:>28} {:<6} Network",
+        "Address", "Alias", "Balance (wei)", "State"
+    );
+    let mut listed = 0;
+    for account in storage.accounts.iter().filter(|a| scope.contains(a)) {
+        let balance = match account.balance {
+            Some(balance) => balance.to_string(),
+            None => "-".to_string(),
+        };
+        println!(
+            "{:<44} {:<24} {:>28} {:<6} {}",
+            account.address,
+            account.alias,
+            balance,
+            match (account.stale, account.is_available(now)) {
+                (true, _) => "stale",
+                (false, false) => "leased",
+                (false, true) => "ok",
+            },
+            network_label(account)
+        );
+        listed += 1;
+    }
+    println!("{} of {} stored accounts", listed, storage.accounts.len());
+    Ok(())
+}
+
+/// Print how many accounts in `scope` each network has, usable, leased and stale
+pub fn count_accounts(scope: &AccountScope) -> Result<(), Box<dyn std::error::Error>> {
+    let storage = load_accounts_from_file_internal()?;
+    let now = now_millis();
+    // (usable, leased, stale) per network
+    let mut counts: std::collections::BTreeMap<String, (usize, usize, usize)> =
+        std::collections::BTreeMap::new();
+    for account in storage.accounts.iter().filter(|a| scope.contains(a)) {
+        let (usable, leased, stale) = counts.entry(network_label(account)).or_default();
+        if account.stale {
+            *stale += 1;
+        } else if account.is_available(now) {
+            *usable += 1;
+        } else {
+            *leased += 1;
+        }
+    }
+
+    println!(
+        "{:<48} {:>8} {:>8} {:>8}",
+        "Network", "Usable", "Leased", "Stale"
+    );
+    for (network, (usable, leased, stale)) in &counts {
+        println!("{:<48} {:>8} {:>8} {:>8}", network, usable, leased, stale);
+    }
+    let usable: usize = counts.values().map(|(usable, _, _)| usable).sum();
+    let leased: usize = counts.values().map(|(_, leased, _)| leased).sum();
+    let stale: usize = counts.values().map(|(_, _, stale)| stale).sum();
+    println!("{:<48} {:>8} {:>8} {:>8}", "Total", usable, leased, stale);
+    Ok(())
+}
+
+/// Write the addresses of the usable accounts in `scope` one per line, or
+/// `address,private_key` lines with `with_keys`, to `output` or stdout
+pub fn export_accounts(
+    scope: &AccountScope,
+    with_keys: bool,
+    output: Option<&str>,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let storage = load_accounts_from_file_internal()?;
+    let lines: Vec<String> = storage
+        .accounts
+        .iter()
+        .filter(|account| !account.stale && scope.contains(account))
+        .map(|account| {
+            if with_keys {
+                format!("{},0x{}", account.address, account.private_key)
+            } else {
+                account.address.clone()
+            }
+        })
+        .collect();
+
+    match output {
+        Some(path) => {
+            std::fs::write(path, lines.join("\n") + "\n")?;
+            println!("Exported {} accounts to {}", lines.len(), path);
+        }
+        None => {
+            for line in &lines {
+                println!("{}", line);
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Reads a key list: one hex private key per line, `0x` optional. Lines may
+/// also be `address,private_key` as written by `export_accounts`, blank lines
+/// and `#` comments are skipped. Returns the keys and the lines that were not keys.
+pub fn parse_key_list(content: &str) -> (Vec<PrivateKeySigner>, Vec<usize>) {
+    let mut signers = Vec::new();
+    let mut invalid = Vec::new();
+    for (index, line) in content.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let key = line.rsplit(',').next().unwrap_or(line).trim();
+        let stored = StoredAccount {
+            private_key: key.trim_start_matches("0x").to_string(),
+            address: String::new(),
+            alias: String::new(),
+            registration_tx_id: None,
+            registered_at: 0,
+            stale: false,
+            network_id: None,
+            gateway_url: None,
+            balance: None,
+            balance_updated_at: None,
+            lease: None,
+        };
+        match stored.signer() {
+            Ok(signer) => signers.push(signer),
+            Err(_) => invalid.push(index + 1),
+        }
+    }
+    (signers, invalid)
+}
+
+/// Add the keys listed in `path` to the store as accounts of `network`
+pub async fn import_accounts(
+    path: &str,
+    network: &NetworkKey,
+    verbosity: &bool,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let content = std::fs::read_to_string(path)?;
+    let (signers, invalid) = parse_key_list(&content);
+    for line in &invalid {
+        eprintln!("{}:{}: not a private key, skipped", path, line);
+    }
+
+    let known: Vec<String> = load_accounts_from_file_internal()
+        .map(|storage| storage.accounts.into_iter().map(|a| a.address).collect())
+        .unwrap_or_default();
+    let listed = signers.len();
+    let accounts: Vec<(PrivateKeySigner, String, Option<String>)> = signers
+        .into_iter()
+        .filter(|signer| !known.contains(&signer.address().to_string()))
+        .map(|signer| (signer, String::new(), None))
+        .collect();
+
+    if !accounts.is_empty() {
+        save_accounts_to_file(&accounts, network, false, verbosity).await?;
+    }
+    println!(
+        "Imported {} accounts for {}, {} already stored, {} invalid lines",
+        accounts.len(),
+        network,
+        listed - accounts.len(),
+        invalid.len()
+    );
+    Ok(())
+}
+
+/// Look the accounts of `network` up and record their balances, accounts the
+/// network does not know are marked stale
+pub async fn refresh_balances(
+    network: &NetworkKey,
+    verbosity: &bool,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let results = check_stored_accounts(&load_accounts_from_file_internal()?, network).await;
+    let now = now_millis();
+
+    let (mut found, mut missing, mut unreachable) = (0, 0, 0);
+    let mut total_balance: u128 = 0;
+    update_storage(|storage| {
+        for account in storage.accounts.iter_mut() {
+            let Some(status) = results.get(&account.address) else {
+                continue;
+            };
+            match status {
+                OnChainStatus::Found { balance } => {
+                    account.balance = Some(*balance);
+                    account.balance_updated_at = Some(now);
+                    total_balance = total_balance.saturating_add(*balance);
+                    found += 1;
+                }
+                OnChainStatus::Missing => {
+                    account.stale = true;
+                    missing += 1;
+                }
+                OnChainStatus::Unreachable(e) => {
+                    verbose(
+                        verbosity,
+                        &format!("Failed to look up {}: {}", account.address, e),
+                    );
+                    unreachable += 1;
+                }
+            }
+        }
+    })?;
+
+    println!(
+        "Refreshed {} accounts of {}: {} found holding {} wei, {} missing, {} unreachable",
+        results.len(),
+        network,
+        found,
+        total_balance,
+        missing,
+        unreachable
+    );
+    Ok(())
+}
+
+#[derive(Default)]
+struct PruneCounts {
+    missing: usize,
+    low_balance: usize,
+    unreachable: usize,
+    in_use: usize,
+}
+
+/// Drop the accounts of `network` the network does not know or that hold less
+/// than `min_balance`. Unreachable accounts are kept, nothing is known about
+/// them, and so are accounts leased by a running test.
+pub async fn prune_accounts(
+    network: &NetworkKey,
+    min_balance: u128,
+    dry_run: bool,
+    verbosity: &bool,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let mut storage = load_accounts_from_file_internal()?;
+    let results = check_stored_accounts(&storage, network).await;
+    let now = now_millis();
+
+    let counts = if dry_run {
+        prune_storage(&mut storage, &results, min_balance, now, verbosity)
+    } else {
+        update_storage(|storage| prune_storage(storage, &results, min_balance, now, verbosity))?
+    };
+
+    println!(
+        "{} {} of {} accounts of {}: {} missing, {} below {} wei, {} unreachable and {} in use kept",
+        if dry_run { "Would prune" } else { "Pruned" },
+        counts.missing + counts.low_balance,
+        results.len(),
+        network,
+        counts.missing,
+        counts.low_balance,
+        min_balance,
+        counts.unreachable,
+        counts.in_use
+    );
+    Ok(())
+}
+
+fn prune_storage(
+    storage: &mut AccountsStorage,
+    results: &std::collections::HashMap<String, OnChainStatus>,
+    min_balance: u128,
+    now: u128,
+    verbosity: &bool,
+) -> PruneCounts {
+    let mut counts = PruneCounts::default();
+    storage.accounts.retain_mut(|account| {
+        let Some(status) = results.get(&account.address) else {
+            return true;
+        };
+        if account.lease.as_ref().is_some_and(|l| l.is_active(now)) {
+            counts.in_use += 1;
+            return true;
+        }
+        match status {
+            OnChainStatus::Found { balance } if *balance < min_balance => {
+                verbose(
+                    verbosity,
+                    &format!("Pruning {}: balance {} wei", account.address, balance),
+                );
+                counts.low_balance += 1;
+                false
+            }
+            OnChainStatus::Found { balance } => {
+                account.balance = Some(*balance);
+                account.balance_updated_at = Some(now);
+                account.stale = false;
+                true
+            }
+            OnChainStatus::Missing => {
+                verbose(
+                    verbosity,
+                    &format!("Pruning {}: not on the network", account.address),
+                );
+                counts.missing += 1;
+                false
+            }
+            OnChainStatus::Unreachable(e) => {
+                verbose(
+                    verbosity,
+                    &format!("Keeping {}, lookup failed: {}", account.address, e),
+                );
+                counts.unreachable += 1;
+                true
+            }
+        }
+    });
+    counts
+}
+
+/// Check out every account of `network` no running test holds a lease on,
+/// stale ones included, so no run picks them up until `release_leases`.
+/// Returns their signers and the number of accounts left out for being in use.
+pub fn lease_idle_accounts(
+    network: &NetworkKey,
+) -> Result<(Vec<PrivateKeySigner>, usize), Box<dyn std::error::Error>> {
+    if !std::path::Path::new(ACCOUNTS_FILE).exists() {
+        return Err(format!("{} does not exist", ACCOUNTS_FILE).into());
+    }
+    let now = now_millis();
+    let (leased, in_use) =
+        update_storage(|storage| lease_idle(storage, network, &run_lease(now), now))?;
+
+    let mut idle = Vec::new();
+    for account in &leased {
+        if let Ok(signer) = account.signer() {
+            idle.push(signer);
+        }
+    }
+    Ok((idle, in_use))
+}
+
+/// Lease the accounts of `network` in `storage` without an active lease, returns
+/// copies of them and how many were leased by someone else
+fn lease_idle(
+    storage: &mut AccountsStorage,
+    network: &NetworkKey,
+    lease: &Lease,
+    now: u128,
+) -> (Vec<StoredAccount>, usize) {
+    let mut in_use = 0;
+    let mut leased = Vec::new();
+    for account in storage
+        .accounts
+        .iter_mut()
+        .filter(|account| !account.is_legacy() && account.belongs_to(network))
+    {
+        if account.lease.as_ref().is_some_and(|l| l.is_active(now)) {
+            in_use += 1;
+        } else {
+            account.lease = Some(lease.clone());
+            leased.push(account.clone());
+        }
+    }
+    (leased, in_use)
+}
+
+/// Look up every account of `network` in `storage` that is keyed to it, legacy
+/// accounts are left out since they may belong to another network
+async fn check_stored_accounts(
+    storage: &AccountsStorage,
+    network: &NetworkKey,
+) -> std::collections::HashMap<String, OnChainStatus> {
+    let wallets: Vec<PrivateKeySigner> = storage
+        .accounts
+        .iter()
+        .filter(|account| !account.is_legacy() && account.belongs_to(network))
+        .filter_map(|account| account.signer().ok())
+        .collect();
+
+    check_accounts(wallets, &network.gateway_url)
+        .await
+        .into_iter()
+        .map(|(wallet, status)| (wallet.address().to_string(), status))
+        .collect()
+}
+
+/// Encrypt a plaintext account store in place with the store passphrase,
+/// returns the number of accounts it holds
+pub fn encrypt_store() -> Result<usize, Box<dyn std::error::Error>> {
+    let _lock = StoreLock::acquire()?;
+    let content = std::fs::read_to_string(ACCOUNTS_FILE)?;
+    if keystore::is_encrypted(&content) {
+        return Err(format!("{} is already encrypted", ACCOUNTS_FILE).into());
+    }
+    let storage: AccountsStorage = serde_json::from_str(&content)?;
+
+    let sealed = keystore::seal(content.as_bytes(), keystore::passphrase()?, None)?;
+    replace_file(&sealed)?;
+    Ok(storage.accounts.len())
+}
+
+/// Internal function to load accounts storage from file, decrypting it when
+/// it is an encrypted keystore
+pub fn load_accounts_from_file_internal() -> Result<AccountsStorage, Box<dyn std::error::Error>> {
+    let content = std::fs::read_to_string(ACCOUNTS_FILE)?;
+    if keystore::is_encrypted(&content) {
+        let (plaintext, _) = keystore::open(&content, keystore::passphrase()?)?;
+        return Ok(serde_json::from_slice(&plaintext)?);
+    }
+    let storage: AccountsStorage = serde_json::from_str(&content)?;
+    Ok(storage)
+}
+
+/// Exclusive lock on the store across processes, held from reading the store
+/// to writing it back so concurrent runs don't lose each other's changes
+struct StoreLock(std::fs::File);
+
+impl StoreLock {
+    fn acquire() -> Result<Self, Box<dyn std::error::Error>> {
+        std::fs::create_dir_all("./artifacts")?;
+        let file = std::fs::OpenOptions::new()
+            .create(true)
+            .truncate(false)
+            .write(true)
+            .open(LOCK_FILE)?;
+        file.lock_exclusive()?;
+        Ok(StoreLock(file))
+    }
+}
+
+impl Drop for StoreLock {
+    fn drop(&mut self) {
+        let _ = FileExt::unlock(&self.0);
+    }
+}
+
+/// Read, change and write back the store under the store lock. A store that
+/// exists but cannot be read (e.g. a wrong passphrase) is left untouched.
+fn update_storage<R>(
+    update: impl FnOnce(&mut AccountsStorage) -> R,
+) -> Result<R, Box<dyn std::error::Error>> {
+    let _lock = StoreLock::acquire()?;
+    let mut storage = if std::path::Path::new(ACCOUNTS_FILE).exists() {
+        load_accounts_from_file_internal()?
+    } else {
+        AccountsStorage {
+            accounts: Vec::new(),
+            last_updated: now_millis(),
+        }
+    };
+    let result = update(&mut storage);
+    write_storage(&mut storage)?;
+    Ok(result)
+}
+
+/// Write the store in the format it is in on disk. A new store is encrypted
+/// when a passphrase is set in the environment. Callers hold the store lock.
+fn write_storage(storage: &mut AccountsStorage) -> Result<(), Box<dyn std::error::Error>> {
+    storage.last_updated = now_millis();
+
+    let json = serde_json::to_string_pretty(storage)?;
+    let encrypt_with_salt = match std::fs::read_to_string(ACCOUNTS_FILE) {
+        Ok(existing) if keystore::is_encrypted(&existing) => Some(keystore::salt_of(&existing)),
+        Ok(_) => None,
+        Err(_) if keystore::passphrase_configured() => Some(None),
+        Err(_) => None,
+    };
+    let content = match encrypt_with_salt {
+        Some(salt) => keystore::seal(json.as_bytes(), keystore::passphrase()?, salt.as_deref())?,
+        None => json,
+    };
+    replace_file(&content)
+}
+
+/// Swap in the new store with a rename, readers see the old or the new file
+/// but never a half written one
+fn replace_file(content: &str) -> Result<(), Box<dyn std::error::Error>> {
+    let temp_path = format!("{}.{}.tmp", ACCOUNTS_FILE, std::process::id());
+    let mut temp = std::fs::File::create(&temp_path)?;
+    temp.write_all(content.as_bytes())?;
+    temp.sync_all()?;
+    std::fs::rename(&temp_path, ACCOUNTS_FILE)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_legacy_store_loads_and_matches_any_network() {
+        let storage: AccountsStorage = serde_json::from_str(
+            r#"{
+                "accounts": [{
+                    "private_key": "4c0883a69102937d6231471b5dbb6204fe5129617082792ae468d01a3f362318",
+                    "address": "0x2c7536E3605D9C16a7a3D7b1898e529396a65c23",
+                    "alias": "abc",
+                    "registration_tx_id": null,
+                    "registered_at": 1700000000000
+                }],
+                "last_updated": 1700000000000
+            }"#,
+        )
+        .unwrap();
+
+        let account = &storage.accounts[0];
+        assert!(account.is_legacy());
+        assert!(!account.stale);
+        assert!(account.belongs_to(&NetworkKey::new("devnet", "http://0.0.0.0:3030")));
+        assert_eq!(
+            account.signer().unwrap().address().to_string(),
+            account.address
+        );
+    }
+
+    #[test]
+    fn test_accounts_only_match_their_network() {
+        let account = StoredAccount {
+            private_key: String::new(),
+            address: String::new(),
+            alias: String::new(),
+            registration_tx_id: None,
+            registered_at: 0,
+            stale: false,
+            network_id: Some("devnet".to_string()),
+            gateway_url: Some("https://dev.liberdus.com:3030".to_string()),
+            balance: None,
+            balance_updated_at: None,
+            lease: None,
+        };
+
+        assert!(account.belongs_to(&NetworkKey::new("devnet", "https://dev.liberdus.com:3030/")));
+        assert!(!account.belongs_to(&NetworkKey::new("testnet", "https://dev.liberdus.com:3030")));
+        assert!(!account.belongs_to(&NetworkKey::new("devnet", "http://0.0.0.0:3030")));
+    }
+
+    #[test]
+    fn test_checkouts_are_disjoint_until_leases_expire() {
+        let network = NetworkKey::new("devnet", "http://0.0.0.0:3030");
+        let signers: Vec<PrivateKeySigner> = (0..5).map(|_| PrivateKeySigner::random()).collect();
+        let mut storage = AccountsStorage {
+            accounts: signers
+                .iter()
+                .map(|signer| StoredAccount {
+                    private_key: hex::encode(signer.to_bytes()),
+                    address: signer.address().to_string(),
+                    alias: String::new(),
+                    registration_tx_id: None,
+                    registered_at: 0,
+                    stale: false,
+                    network_id: Some(network.network_id.clone()),
+                    gateway_url: Some(network.gateway_url.clone()),
+                    balance: None,
+                    balance_updated_at: None,
+                    lease: None,
+                })
+                .collect(),
+            last_updated: 0,
+        };
+        let lease = |holder: &str| Lease {
+            holder: holder.to_string(),
+            expires_at: 1_000,
+        };
+
+        let first = checkout_accounts(&mut storage, &network, 3, &lease("a"), 0);
+        let second = checkout_accounts(&mut storage, &network, 3, &lease("b"), 0);
+        assert_eq!(first.len(), 3);
+        assert_eq!(second.len(), 2);
+        assert!(first
+            .iter()
+            .all(|a| second.iter().all(|b| a.address != b.address)));
+        assert!(checkout_accounts(&mut storage, &network, 3, &lease("c"), 500).is_empty());
+
+        // a run that never released its accounts loses them after the timeout
+        let third = checkout_accounts(&mut storage, &network, 5, &lease("c"), 1_000);
+        assert_eq!(third.len(), 5);
+        assert!(third
+            .iter()
+            .all(|a| a.lease.as_ref().unwrap().holder == "c"));
+    }
+
+    #[test]
+    fn test_imported_accounts_can_be_checked_out() {
+        let network = NetworkKey::new("devnet", "http://0.0.0.0:3030");
+        let mut storage = AccountsStorage {
+            accounts: Vec::new(),
+            last_updated: 0,
+        };
+        let imported: Vec<(PrivateKeySigner, String, Option<String>)> = (0..3)
+            .map(|_| (PrivateKeySigner::random(), String::new(), None))
+            .collect();
+        assert_eq!(add_accounts(&mut storage, &imported, &network, None, 0), 3);
+        assert_eq!(add_accounts(&mut storage, &imported, &network, None, 0), 3);
+        assert!(storage.accounts.iter().all(|a| a.lease.is_none()));
+
+        let lease = Lease {
+            holder: "run".to_string(),
+            expires_at: 1_000,
+        };
+        let checked_out = checkout_accounts(&mut storage, &network, 5, &lease, 0);
+        assert_eq!(checked_out.len(), 3);
+
+        // accounts a run registers are its own until it releases them
+        let registered = vec![(PrivateKeySigner::random(), String::new(), None)];
+        add_accounts(&mut storage, &registered, &network, Some(lease.clone()), 0);
+        assert!(checkout_accounts(&mut storage, &network, 5, &lease, 0).is_empty());
+    }
+
+    #[test]
+    fn test_idle_accounts_are_leased_for_a_sweep() {
+        let network = NetworkKey::new("devnet", "http://0.0.0.0:3030");
+        let mut storage = AccountsStorage {
+            accounts: Vec::new(),
+            last_updated: 0,
+        };
+        let accounts: Vec<(PrivateKeySigner, String, Option<String>)> = (0..4)
+            .map(|_| (PrivateKeySigner::random(), String::new(), None))
+            .collect();
+        add_accounts(&mut storage, &accounts, &network, None, 0);
+        storage.accounts[3].stale = true;
+        let lease = |holder: &str| Lease {
+            holder: holder.to_string(),
+            expires_at: 1_000,
+        };
+        let running = checkout_accounts(&mut storage, &network, 1, &lease("run"), 0);
+
+        // stale accounts are swept too, the one a run holds is left alone
+        let (swept, in_use) = lease_idle(&mut storage, &network, &lease("sweep"), 0);
+        assert_eq!((swept.len(), in_use), (3, 1));
+        assert!(swept.iter().all(|a| a.address != running[0].address));
+
+        // a run starting during the sweep finds nothing to check out
+        assert!(checkout_accounts(&mut storage, &network, 4, &lease("next"), 0).is_empty());
+    }
+
+    #[test]
+    fn test_parse_key_list() {
+        let content = "# exported keys\n\
+            4c0883a69102937d6231471b5dbb6204fe5129617082792ae468d01a3f362318\n\
+            \n\
+            0x2c7536E3605D9C16a7a3D7b1898e529396a65c23,0x4c0883a69102937d6231471b5dbb6204fe5129617082792ae468d01a3f362318\n\
+            not-a-key\n";
+        let (signers, invalid) = parse_key_list(content);
+
+        assert_eq!(signers.len(), 2);
+        assert!(signers
+            .iter()
+            .all(|s| s.address().to_string() == "0x2c7536E3605D9C16a7a3D7b1898e529396a65c23"));
+        assert_eq!(invalid, vec![5]);
+    }
+}
@@ -1,16 +1,27 @@
 use crate::{
+    account_store::{self, AccountScope, NetworkKey},
     change_config,
-    load_injector::{self},
-    monitor_server, proxy, stake, transactions,
+    confirmation::{self, ConfirmationConfig},
+    economics,
+    finality::FinalityConfig,
+    funding::{self, FundingConfig, SweepConfig},
+    hd_accounts::HdAccounts,
+    load_injector::{self, SaturationPolicy},
+    load_profile::LoadProfile,
+    monitor_server, proxy, registration, scenario, stake, transactions,
+    workload::{TxKind, WorkloadMix},
 };
 use alloy::signers::local::PrivateKeySigner;
 use clap::{arg, command, ArgAction, ArgGroup, Command};
+use tokio::time::Duration;
 
 pub fn get_commands() -> Command {
     command!() // requires `cargo` feature
         .subcommand(loadtest_subcommand())
         .subcommand(staking_subcommand())
         .subcommand(change_config_subcommand())
+        .subcommand(run_subcommand())
+        .subcommand(accounts_subcommand())
         .subcommand(
             Command::new("tui")
                 .about("Starts the TUI, (still in development)")
@@ -29,6 +40,12 @@ pub async fn execute_command(matches: &clap::ArgMatches) {
         Some(("change_config", sub_m)) => {
             execute_change_config_subcommand(sub_m).await;
         }
+        Some(("run", sub_m)) => {
+            execute_run_subcommand(sub_m).await;
+        }
+        Some(("accounts", sub_m)) => {
+            execute_accounts_subcommand(sub_m).await;
+        }
         _ => {
             panic!("Invalid subcommand provided");
         }
@@ -41,6 +58,266 @@ pub fn verbose(verbosity: &bool, message: &str) {
     }
 }
 
+fn run_subcommand() -> Command {
+    Command::new("run")
+        .about("Run a multi-phase test described in a TOML or JSON scenario file")
+        .arg(
+            arg!(
+                <SCENARIO> "Path to the scenario file (.toml or .json)"
+            )
+            .required(true)
+            .value_parser(|s: &str| s.parse::<String>()),
+        )
+}
+
+async fn execute_run_subcommand(matches: &clap::ArgMatches) {
+    let path = matches
+        .get_one::<String>("SCENARIO")
+        .expect("scenario path is required");
+
+    let scenario = match scenario::Scenario::load(path) {
+        Ok(scenario) => scenario,
+        Err(e) => {
+            eprintln!("Invalid scenario {}: {}", path, e);
+            std::process::exit(1);
+        }
+    };
+
+    scenario::run(scenario).await;
+}
+
+fn accounts_subcommand() -> Command {
+    Command::new("accounts")
+        .about("Inspect and maintain the stored test accounts")
+        .subcommand_required(true)
+        .arg(
+            arg!(
+                --gateway_url <URL> "Gateway URL of the network to work on. (default: http://0.0.0.0:3030)"
+            )
+            .required(false)
+            .global(true)
+            .value_parser(|s: &str| s.parse::<String>()),
+        )
+        .arg(
+            arg!(
+                --network_id <ID> "Network id of the network to work on. (default: $NETWORK_ID)"
+            )
+            .required(false)
+            .global(true)
+            .value_parser(|s: &str| s.parse::<String>()),
+        )
+        .arg(
+            arg!(
+                --verbose "Std out verbosity"
+            )
+            .required(false)
+            .global(true)
+            .action(ArgAction::SetTrue),
+        )
+        .subcommand(
+            Command::new("list")
+                .about("List the stored accounts of the network")
+                .arg(arg!(--all "List the accounts of every network").action(ArgAction::SetTrue)),
+        )
+        .subcommand(
+            Command::new("count")
+                .about("Count the stored accounts of the network, usable and stale")
+                .arg(arg!(--all "Count the accounts of every network").action(ArgAction::SetTrue)),
+        )
+        .subcommand(
+            Command::new("prune")
+                .about("Remove accounts missing on the network or below a balance")
+                .arg(
+                    arg!(
+                        --min_balance <WEI> "Accounts holding less are removed. (default: the fee and toll of one message)"
+                    )
+                    .required(false)
+                    .value_parser(|s: &str| {
+                        s.parse::<u128>()
+                        .map_err(|_| format!("'{}' is not a valid number", s))
+                    }),
+                )
+                .arg(
+                    arg!(
+                        --dry_run "Only report what would be removed"
+                    )
+                    .action(ArgAction::SetTrue),
+                ),
+        )
+        .subcommand(
+            Command::new("export")
+                .about("Export the usable accounts of the network")
+                .arg(
+                    arg!(
+                        --with_keys "Write address,private_key lines instead of addresses"
+                    )
+                    .action(ArgAction::SetTrue),
+                )
+                .arg(arg!(--all "Export the accounts of every network").action(ArgAction::SetTrue))
+                .arg(
+                    arg!(
+                        --output <PATH> "File to write to. (default: stdout)"
+                    )
+                    .required(false)
+                    .value_parser(|s: &str| s.parse::<String>()),
+                ),
+        )
+        .subcommand(
+            Command::new("import")
+                .about("Add accounts of the network from a list of private keys")
+                .arg(
+                    arg!(
+                        <FILE> "File with one hex private key (or address,private_key) per line"
+                    )
+                    .required(true)
+                    .value_parser(|s: &str| s.parse::<String>()),
+                ),
+        )
+        .subcommand(
+            Command::new("refresh")
+                .about("Look up and record the balances of the stored accounts of the network"),
+        )
+        .subcommand(
+            Command::new("sweep")
+                .about("Send the leftover balances of the stored accounts of the network to a collector address")
+                .arg(
+                    arg!(
+                        --to <ADDRESS> "Collector address the balances are sent to"
+                    )
+                    .required(true)
+                    .value_parser(|s: &str| {
+                        s.parse::<alloy::primitives::Address>()
+                        .map_err(|_| format!("'{}' is not a valid address", s))
+                    }),
+                )
+                .arg(
+                    arg!(
+                        --dust <WEI> "Accounts that would send this much or less after the fee are left alone. (default: one transfer fee)"
+                    )
+                    .required(false)
+                    .value_parser(|s: &str| {
+                        s.parse::<u128>()
+                        .map_err(|_| format!("'{}' is not a valid number", s))
+                    }),
+                )
+                .arg(
+                    arg!(
+                        --fee <WEI> "Fee kept back from every balance for its transfer. (default: the network's transaction fee)"
+                    )
+                    .required(false)
+                    .value_parser(|s: &str| {
+                        s.parse::<u128>()
+                        .map_err(|_| format!("'{}' is not a valid number", s))
+                    }),
+                )
+                .arg(
+                    arg!(
+                        --concurrency <NUMBER> "Sweep transfers in flight at once. (default: 16)"
+                    )
+                    .required(false)
+                    .value_parser(|s: &str| {
+                        s.parse::<usize>()
+                        .map_err(|_| format!("'{}' is not a valid number", s))
+                        .and_then(|n| if n == 0 { Err("concurrency must be at least 1".to_string()) } else { Ok(n) })
+                    }),
+                )
+                .arg(
+                    arg!(
+                        --dry_run "Only report what would be swept"
+                    )
+                    .action(ArgAction::SetTrue),
+                ),
+        )
+        .subcommand(
+            Command::new("encrypt").about(
+                "Encrypt a plaintext account store with the passphrase from $ACCOUNTS_PASSPHRASE or a prompt",
+            ),
+        )
+}
+
+async fn execute_accounts_subcommand(matches: &clap::ArgMatches) {
+    let gateway_url = match matches.get_one::<String>("gateway_url") {
+        Some(url) => url,
+        None => &"http://0.0.0.0:3030".to_string(),
+    };
+
+    if let Some(network_id) = matches.get_one::<String>("network_id") {
+        if let Err(e) = transactions::set_network_id(network_id) {
+            eprintln!("{}", e);
+            std::process::exit(1);
+        }
+    }
+
+    let verbosity = matches.get_flag("verbose");
+    let network = NetworkKey::current(gateway_url);
+    let scope = |sub_m: &clap::ArgMatches| {
+        if sub_m.get_flag("all") {
+            AccountScope::All
+        } else {
+            AccountScope::Network(network.clone())
+        }
+    };
+
+    let result = match matches.subcommand() {
+        Some(("list", sub_m)) => account_store::list_accounts(&scope(sub_m)),
+        Some(("count", sub_m)) => account_store::count_accounts(&scope(sub_m)),
+        Some(("prune", sub_m)) => {
+            let min_balance = match sub_m.get_one::<u128>("min_balance") {
+                Some(min_balance) => *min_balance,
+                None => economics::load(gateway_url).await.message_cost(),
+            };
+            account_store::prune_accounts(
+                &network,
+                min_balance,
+                sub_m.get_flag("dry_run"),
+                &verbosity,
+            )
+            .await
+        }
+        Some(("export", sub_m)) => account_store::export_accounts(
+            &scope(sub_m),
+            sub_m.get_flag("with_keys"),
+            sub_m.get_one::<String>("output").map(|s| s.as_str()),
+        ),
+        Some(("import", sub_m)) => {
+            let path = sub_m
+                .get_one::<String>("FILE")
+                .expect("key file is required");
+            account_store::import_accounts(path, &network, &verbosity).await
+        }
+        Some(("refresh", _)) => account_store::refresh_balances(&network, &verbosity).await,
+        Some(("sweep", sub_m)) => {
+            let transfer_fee = match sub_m.get_one::<u128>("fee") {
+                Some(fee) => *fee,
+                None => economics::load(gateway_url).await.transaction_fee,
+            };
+            let config = SweepConfig {
+                collector: *sub_m
+                    .get_one::<alloy::primitives::Address>("to")
+                    .expect("collector address is required"),
+                dust: sub_m.get_one::<u128>("dust").copied().unwrap_or(transfer_fee),
+                transfer_fee,
+                concurrency: sub_m.get_one::<usize>("concurrency").copied().unwrap_or(16),
+                dry_run: sub_m.get_flag("dry_run"),
+            };
+            funding::sweep_accounts(&network, &config, &verbosity).await
+        }
+        Some(("encrypt", _)) => account_store::encrypt_store().map(|count| {
+            println!(
+                "Encrypted {} with {} accounts",
+                account_store::ACCOUNTS_FILE,
+                count
+            )
+        }),
+        _ => panic!("Invalid accounts subcommand provided"),
+    };
+
+    if let Err(e) = result {
+        eprintln!("accounts: {}", e);
+        std::process::exit(1);
+    }
+}
+
 fn change_config_subcommand() -> Command {
     Command::new("change_config")
         .about("Change the configuration of the network")
@@ -140,6 +417,16 @@ fn staking_subcommand() -> Command {
                     .map_err(|_| format!("'{}' is not a valid string", s))
             }),
         )
+        .arg(
+            arg!(
+                --register_retries <NUMBER> "Total retries of failed registrations, each after a backoff. (default: one per account)"
+            )
+            .required(false)
+            .value_parser(|s: &str| {
+                s.parse::<usize>()
+                .map_err(|_| format!("'{}' is not a valid number", s))
+            }),
+        )
         // .arg(
         //     arg!(
         //         --nominee <STRING> "Address of a particular node's account"
@@ -177,6 +464,34 @@ fn staking_subcommand() -> Command {
             .required(false)
             .value_parser(|s: &str| s.parse::<String>()),
         )
+        .arg(
+            arg!(
+                --confirm_timeout <SEC> "Seconds to wait for registered accounts to show up on-chain. (default: 120)"
+            )
+            .required(false)
+            .value_parser(|s: &str| {
+                s.parse::<u64>()
+                .map_err(|_| format!("'{}' is not a valid number", s))
+            }),
+        )
+        .arg(
+            arg!(
+                --confirm_poll_interval <MS> "Milliseconds between account lookups while waiting for registrations. (default: 1000)"
+            )
+            .required(false)
+            .value_parser(|s: &str| {
+                s.parse::<u64>()
+                .map_err(|_| format!("'{}' is not a valid number", s))
+                .and_then(|n| if n == 0 { Err("confirm_poll_interval must be at least 1".to_string()) } else { Ok(n) })
+            }),
+        )
+        .arg(
+            arg!(
+                --min_confirmed <PERCENT> "Start once this percentage of accounts is confirmed and no more are coming in. (default: 100)"
+            )
+            .required(false)
+            .value_parser(confirmation::parse_min_confirmed),
+        )
 }
 
 fn loadtest_subcommand() -> Command {
@@ -186,7 +501,19 @@ fn loadtest_subcommand() -> Command {
             --tx_type <TYPE> "Type of Transaction to test"
         )
         .required(false)
-        .value_parser([ "transfer", "register", "message" ]),
+        .value_parser([ "transfer", "register", "message", "friend" ]),
+    )
+    .arg(
+        arg!(
+            --mix <SPEC> "Mixed workload sharing one wallet pool and TPS budget, e.g. transfer=60,message=35,register=5"
+        )
+        .required(false)
+        .value_parser(|s: &str| s.parse::<WorkloadMix>()),
+    )
+    .group(
+        ArgGroup::new("workload")
+            .args(["tx_type", "mix"])
+            .required(true),
     )
     .arg(
         arg!(
@@ -199,6 +526,13 @@ fn loadtest_subcommand() -> Command {
         }),
 
     )
+    .arg(
+        arg!(
+            --profile <SPEC> "Load profile, overrides --tps. One of constant:TPS, ramp:FROM:TO, step:FROM:TO:STEPS, spike:BASE:PEAK:EVERY:LENGTH, sine:MEAN:AMPLITUDE:PERIOD (times in seconds)"
+        )
+        .required(false)
+        .value_parser(|s: &str| s.parse::<LoadProfile>()),
+    )
     .arg(
         arg!(
             --eoa <NUMBER> "Number of address to create. (default: auto). When auto is used, it will be calculated based on the tps and duration"
@@ -250,22 +584,223 @@ fn loadtest_subcommand() -> Command {
         .required(false)
         .action(ArgAction::SetTrue)
     )
+    .arg(
+        arg!(
+            --concurrency <NUMBER> "Closed-loop mode: number of virtual users that each wait for the gateway response before sending again. Overrides --tps"
+        )
+        .required(false)
+        .value_parser(|s: &str| {
+            s.parse::<usize>()
+            .map_err(|_| format!("'{}' is not a valid number", s))
+            .and_then(|n| if n == 0 { Err("concurrency must be at least 1".to_string()) } else { Ok(n) })
+        }),
+    )
+    .arg(
+        arg!(
+            --max_in_flight <NUMBER> "Cap on pending gateway requests in open-loop mode. (default: 1024)"
+        )
+        .required(false)
+        .value_parser(|s: &str| {
+            s.parse::<usize>()
+            .map_err(|_| format!("'{}' is not a valid number", s))
+            .and_then(|n| if n == 0 { Err("max_in_flight must be at least 1".to_string()) } else { Ok(n) })
+        }),
+    )
+    .arg(
+        arg!(
+            --saturation_policy <POLICY> "What to do when --max_in_flight is reached: drop the send or delay it. (default: delay)"
+        )
+        .required(false)
+        .value_parser(|s: &str| s.parse::<SaturationPolicy>()),
+    )
+    .arg(
+        arg!(
+            --min_balance <WEI> "Reused accounts below this balance are marked stale and replaced. (default: the fee and toll of one message)"
+        )
+        .required(false)
+        .value_parser(|s: &str| {
+            s.parse::<u128>()
+            .map_err(|_| format!("'{}' is not a valid number", s))
+        }),
+    )
+    .arg(
+        arg!(
+            --lease_timeout <SEC> "Seconds reused accounts stay checked out if the run does not release them. (default: duration + 3600)"
+        )
+        .required(false)
+        .value_parser(|s: &str| {
+            s.parse::<u64>()
+            .map_err(|_| format!("'{}' is not a valid number", s))
+        }),
+    )
+    .arg(
+        arg!(
+            --mnemonic <PHRASE> "Derive the accounts from this BIP-39 mnemonic, accounts already on-chain are not registered again"
+        )
+        .required(false)
+        .conflicts_with("reuse_accounts")
+        .value_parser(|s: &str| s.parse::<String>()),
+    )
+    .arg(
+        arg!(
+            --mnemonic_start <INDEX> "First derivation index, accounts start..start+eoa are used. (default: 0)"
+        )
+        .required(false)
+        .requires("mnemonic")
+        .value_parser(|s: &str| {
+            s.parse::<u32>()
+            .map_err(|_| format!("'{}' is not a valid index", s))
+        }),
+    )
+    .arg(
+        arg!(
+            --register_retries <NUMBER> "Total retries of failed registrations, each after a backoff. (default: one per account)"
+        )
+        .required(false)
+        .value_parser(|s: &str| {
+            s.parse::<usize>()
+            .map_err(|_| format!("'{}' is not a valid number", s))
+        }),
+    )
+    .arg(
+        arg!(
+            --fund <WEI> "Top every account up to this balance from the funding key before injecting"
+        )
+        .required(false)
+        .value_parser(|s: &str| {
+            s.parse::<u128>()
+            .map_err(|_| format!("'{}' is not a valid number", s))
+        }),
+    )
+    .arg(
+        arg!(
+            --funding_key <HEX> "Private key of the funded account that pays for --fund. (default: $FUNDING_KEY)"
+        )
+        .required(false)
+        .requires("fund")
+        .value_parser(|s: &str| funding::parse_funding_key(s)),
+    )
+    .arg(
+        arg!(
+            --funding_fanout <N> "Accounts every funded account passes funds on to. (default: 8)"
+        )
+        .required(false)
+        .requires("fund")
+        .value_parser(|s: &str| {
+            s.parse::<usize>()
+            .map_err(|_| format!("'{}' is not a valid number", s))
+            .and_then(|n| if n == 0 { Err("funding_fanout must be at least 1".to_string()) } else { Ok(n) })
+        }),
+    )
+    .arg(
+        arg!(
+            --funding_fee <WEI> "Fee reserved for every funding transfer. (default: the network's transaction fee)"
+        )
+        .required(false)
+        .requires("fund")
+        .value_parser(|s: &str| {
+            s.parse::<u128>()
+            .map_err(|_| format!("'{}' is not a valid number", s))
+        }),
+    )
+    .arg(
+        arg!(
+            --funding_timeout <SEC> "Seconds to wait for one round of funding transfers to land. (default: 60)"
+        )
+        .required(false)
+        .requires("fund")
+        .value_parser(|s: &str| {
+            s.parse::<u64>()
+            .map_err(|_| format!("'{}' is not a valid number", s))
+        }),
+    )
+    .arg(
+        arg!(
+            --track_finality "Poll the gateway for the receipt of every accepted transaction and report finality latency"
+        )
+        .required(false)
+        .action(ArgAction::SetTrue)
+    )
+    .arg(
+        arg!(
+            --finality_timeout <SEC> "Seconds to wait for an accepted transaction to be applied. (default: 60)"
+        )
+        .required(false)
+        .requires("track_finality")
+        .value_parser(|s: &str| {
+            s.parse::<u64>()
+            .map_err(|_| format!("'{}' is not a valid number", s))
+            .and_then(|n| if n == 0 { Err("finality_timeout must be at least 1".to_string()) } else { Ok(n) })
+        }),
+    )
+    .arg(
+        arg!(
+            --finality_poll_interval <MS> "Milliseconds between receipt polls of one transaction. (default: 1000)"
+        )
+        .required(false)
+        .requires("track_finality")
+        .value_parser(|s: &str| {
+            s.parse::<u64>()
+            .map_err(|_| format!("'{}' is not a valid number", s))
+            .and_then(|n| if n == 0 { Err("finality_poll_interval must be at least 1".to_string()) } else { Ok(n) })
+        }),
+    )
+    .arg(
+        arg!(
+            --confirm_timeout <SEC> "Seconds to wait for registered accounts to show up on-chain. (default: 120)"
+        )
+        .required(false)
+        .value_parser(|s: &str| {
+            s.parse::<u64>()
+            .map_err(|_| format!("'{}' is not a valid number", s))
+        }),
+    )
+    .arg(
+        arg!(
+            --confirm_poll_interval <MS> "Milliseconds between account lookups while waiting for registrations. (default: 1000)"
+        )
+        .required(false)
+        .value_parser(|s: &str| {
+            s.parse::<u64>()
+            .map_err(|_| format!("'{}' is not a valid number", s))
+            .and_then(|n| if n == 0 { Err("confirm_poll_interval must be at least 1".to_string()) } else { Ok(n) })
+        }),
+    )
+    .arg(
+        arg!(
+            --min_confirmed <PERCENT> "Start once this percentage of accounts is confirmed and no more are coming in. (default: 90)"
+        )
+        .required(false)
+        .value_parser(confirmation::parse_min_confirmed),
+    )
 }
 
 async fn execute_loadtest_subcommand(matches: &clap::ArgMatches) {
-    let tx_type = match matches.get_one::<String>("tx_type") {
-        Some(tx_type) => tx_type.to_string(),
-        None => panic!("No tx_type provided"),
+    let (tx_type, mix) = match (
+        matches.get_one::<String>("tx_type"),
+        matches.get_one::<WorkloadMix>("mix"),
+    ) {
+        (Some(tx_type), _) => {
+            let kind = tx_type.parse::<TxKind>().expect("Invalid tx_type provided");
+            (tx_type.to_string(), WorkloadMix::single(kind))
+        }
+        (None, Some(mix)) => ("mixed".to_string(), mix.clone()),
+        (None, None) => panic!("No tx_type provided"),
     };
 
     let tps = matches.get_one::<usize>("tps").unwrap_or(&1);
 
     let duration = matches.get_one::<usize>("duration").unwrap_or(&60);
 
+    let profile = match matches.get_one::<LoadProfile>("profile") {
+        Some(profile) => profile.clone(),
+        None => LoadProfile::Constant { tps: *tps as f64 },
+    };
+
     let eoa = match matches.get_one::<usize>("eoa") {
         Some(eoa) => eoa,
         None => {
-            let total_tx = (tps * duration) as f64;
+            let total_tx = profile.expected_transactions(*duration as f64);
 
             &((total_tx / 2.0).round() as usize)
         }
@@ -282,30 +817,127 @@ async fn execute_loadtest_subcommand(matches: &clap::ArgMatches) {
 
     let reuse_accounts = matches.get_flag("reuse_accounts");
 
+    let concurrency = matches.get_one::<usize>("concurrency").copied();
+
+    let max_in_flight = matches.get_one::<usize>("max_in_flight").copied();
+
+    let saturation_policy = matches
+        .get_one::<SaturationPolicy>("saturation_policy")
+        .copied()
+        .unwrap_or_default();
+
+    economics::load(gateway_url).await;
+
+    let min_balance = matches
+        .get_one::<u128>("min_balance")
+        .copied()
+        .unwrap_or_else(|| economics::current().message_cost());
+
+    let lease_timeout = match matches.get_one::<u64>("lease_timeout") {
+        Some(timeout) => Duration::from_secs(*timeout),
+        None => account_store::DEFAULT_LEASE_TIMEOUT + Duration::from_secs(*duration as u64),
+    };
+    account_store::set_lease_timeout(lease_timeout).expect("lease timeout is set once");
+
+    let mnemonic = matches.get_one::<String>("mnemonic").map(|phrase| {
+        let start = matches.get_one::<u32>("mnemonic_start").unwrap_or(&0);
+        match HdAccounts::new(phrase, *start).and_then(|hd_accounts| {
+            hd_accounts.check_range(*eoa)?;
+            Ok(hd_accounts)
+        }) {
+            Ok(hd_accounts) => hd_accounts,
+            Err(e) => {
+                eprintln!("{}", e);
+                std::process::exit(1);
+            }
+        }
+    });
+
+    if let Some(retries) = matches.get_one::<usize>("register_retries") {
+        registration::set_register_retries(*retries).expect("register retries are set once");
+    }
+
+    let funding = matches.get_one::<u128>("fund").map(|target_balance| {
+        let funder = match matches.get_one::<PrivateKeySigner>("funding_key") {
+            Some(funder) => Ok(funder.clone()),
+            None => funding::funding_key_from_env().unwrap_or_else(|| {
+                Err(format!(
+                    "--fund needs --funding_key or {}",
+                    funding::FUNDING_KEY_ENV
+                ))
+            }),
+        };
+        let funder = funder.unwrap_or_else(|e| {
+            eprintln!("{}", e);
+            std::process::exit(1);
+        });
+        let mut config = FundingConfig::new(funder, *target_balance);
+        if let Some(fanout) = matches.get_one::<usize>("funding_fanout") {
+            config.fanout = *fanout;
+        }
+        if let Some(fee) = matches.get_one::<u128>("funding_fee") {
+            config.transfer_fee = *fee;
+        }
+        if let Some(timeout) = matches.get_one::<u64>("funding_timeout") {
+            config.round_timeout = Duration::from_secs(*timeout);
+        }
+        config
+    });
+
+    let finality = if matches.get_flag("track_finality") {
+        let mut config = FinalityConfig::default();
+        if let Some(timeout) = matches.get_one::<u64>("finality_timeout") {
+            config.timeout = Duration::from_secs(*timeout);
+        }
+        if let Some(interval) = matches.get_one::<u64>("finality_poll_interval") {
+            config.poll_interval = Duration::from_millis(*interval);
+        }
+        Some(config)
+    } else {
+        None
+    };
+
     let args = load_injector::LoadInjectParams {
         tx_type,
         eoa_tps: *eoa_tps,
-        tps: *tps,
+        profile,
         duration: *duration,
         eoa: *eoa,
         gateway_url: gateway_url.to_string(),
         verbosity: *verbosity,
         reuse_accounts,
+        concurrency,
+        mix,
+        max_in_flight,
+        saturation_policy,
+        finality,
+        confirmation: confirmation_config(matches, ConfirmationConfig::default()),
+        min_balance,
+        mnemonic,
+        funding,
     };
 
     println!("{:?}", args);
 
-    match args.tx_type.as_str() {
-        "transfer" => {
-            load_injector::transfer(args).await;
-        }
-        "message" => {
-            load_injector::message(args).await;
-        }
-        _ => {
-            panic!("Invalid tx_type provided");
-        }
+    load_injector::run(args).await;
+}
+
+/// Registration confirmation settings from the command line, on top of `defaults`
+fn confirmation_config(
+    matches: &clap::ArgMatches,
+    defaults: ConfirmationConfig,
+) -> ConfirmationConfig {
+    let mut config = defaults;
+    if let Some(timeout) = matches.get_one::<u64>("confirm_timeout") {
+        config.timeout = Duration::from_secs(*timeout);
     }
+    if let Some(interval) = matches.get_one::<u64>("confirm_poll_interval") {
+        config.poll_interval = Duration::from_millis(*interval);
+    }
+    if let Some(min_confirmed) = matches.get_one::<f64>("min_confirmed") {
+        config.min_confirmed = *min_confirmed;
+    }
+    config
 }
 
 async fn execute_staking_subcommand(matches: &clap::ArgMatches) {
@@ -355,10 +987,22 @@ async fn execute_staking_subcommand(matches: &clap::ArgMatches) {
         _ => {}
     }
 
+    if let Some(retries) = matches.get_one::<usize>("register_retries") {
+        registration::set_register_retries(*retries).expect("register retries are set once");
+    }
+
     let args = stake::StakingParams {
         gateway_url: gateway_url.to_string(),
         verbose: *verbosity,
         stake_amount: *amount,
+        // every nominee needs its own confirmed nominator
+        confirmation: confirmation_config(
+            matches,
+            ConfirmationConfig {
+                min_confirmed: 100.0,
+                ..Default::default()
+            },
+        ),
     };
 
     let _ = stake::stake(nominees, &args).await;
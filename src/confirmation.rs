@@ -0,0 +1,225 @@
+use crate::{cli::verbose, proxy, utils};
+use alloy::{primitives::Address, signers::local::PrivateKeySigner};
+use std::sync::Arc;
+use tokio::sync::Semaphore;
+use tokio::task::JoinSet;
+use tokio::time::{Duration, Instant};
+
+/// Account lookups allowed in flight at once while waiting for registrations
+const MAX_CONCURRENT_POLLS: usize = 64;
+
+/// Rounds in a row without a new confirmation before the wait may end early
+const STAGNANT_ROUNDS: u32 = 5;
+
+/// How long to wait for registered accounts to show up on-chain, how often to
+/// look and how many are enough to go ahead
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ConfirmationConfig {
+    pub timeout: Duration,
+    pub poll_interval: Duration,
+    /// Percentage of the accounts that must be confirmed before the wait may end early
+    pub min_confirmed: f64,
+}
+
+impl Default for ConfirmationConfig {
+    fn default() -> Self {
+        ConfirmationConfig {
+            timeout: Duration::from_secs(120),
+            poll_interval: Duration::from_millis(1000),
+            min_confirmed: 90.0,
+        }
+    }
+}
+
+impl ConfirmationConfig {
+    /// Number of confirmed accounts out of `total` that satisfies `min_confirmed`
+    pub fn threshold(&self, total: usize) -> usize {
+        ((total as f64 * self.min_confirmed / 100.0).ceil() as usize).min(total)
+    }
+}
+
+/// Parses a `min_confirmed` percentage
+pub fn parse_min_confirmed(s: &str) -> Result<f64, String> {
+    match s.parse::<f64>() {
+        Ok(n) if n > 0.0 && n <= 100.0 => Ok(n),
+        _ => Err(format!("'{}' is not a percentage between 0 and 100", s)),
+    }
+}
+
+/// Poll `/account/{addr}` until registrations land and return the accounts found on-chain.
+///
+/// Accounts not found yet are asked for again every round, so a slow network
+/// does not lose them. The wait ends when every account is confirmed, when at
+/// least `min_confirmed` percent are and `STAGNANT_ROUNDS` rounds in a row brought
+/// no new ones, or at the deadline.
+pub async fn wait_for_registrations(
+    wallets: Vec<PrivateKeySigner>,
+    gateway_url: &str,
+    config: &ConfirmationConfig,
+    verbosity: &bool,
+) -> Vec<PrivateKeySigner> {
+    let total = wallets.len();
+    let threshold = config.threshold(total);
+    let start = Instant::now();
+    let deadline = start + config.timeout;
+
+    // uses ARC internally
+    let http_client = reqwest::Client::builder()
+        .danger_accept_invalid_certs(true)
+        .build()
+        .unwrap();
+    let polls = Arc::new(Semaphore::new(MAX_CONCURRENT_POLLS));
+
+    let mut pending = wallets;
+    let mut confirmed = Vec::with_capacity(total);
+    let mut stagnant_rounds = 0;
+
+    loop {
+        let mut lookups = JoinSet::new();
+        for wallet in pending.drain(..) {
+            let http_client = http_client.clone();
+            let polls = Arc::clone(&polls);
+            let gateway_url = gateway_url.to_string();
+            lookups.spawn(async move {
+                let _permit = polls.acquire().await.expect("poll semaphore closed");
+                let on_chain = account_exists(&http_client, &gateway_url, &wallet).await;
+                (wallet, on_chain)
+            });
+        }
+
+        let mut newly_confirmed = 0;
+        while let Some(lookup) = lookups.join_next().await {
+            let (wallet, on_chain) = lookup.expect("account lookup panicked");
+            if on_chain {
+                verbose(
+                    verbosity,
+                    &format!(
+                        "Successful Register {}",
+                        utils::to_shardus_address(&wallet.address().to_string())
+                    ),
+                );
+                confirmed.push(wallet);
+                newly_confirmed += 1;
+            } else {
+                pending.push(wallet);
+            }
+        }
+        utils::stdout_confirm_progress(total, confirmed.len());
+
+        if pending.is_empty() {
+            break;
+        }
+        if newly_confirmed == 0 {
+            stagnant_rounds += 1;
+        } else {
+            stagnant_rounds = 0;
+        }
+        // the stragglers have stopped coming in, don't hold the test back for them
+        if confirmed.len() >= threshold && stagnant_rounds >= STAGNANT_ROUNDS {
+            break;
+        }
+        let next_poll = Instant::now() + config.poll_interval;
+        if next_poll > deadline {
+            break;
+        }
+        tokio::time::sleep_until(next_poll).await;
+    }
+
+    println!(
+        "\nConfirmed {} of {} accounts in {:.1}s",
+        confirmed.len(),
+        total,
+        start.elapsed().as_secs_f64()
+    );
+    if confirmed.len() < threshold {
+        println!(
+            "Deadline reached below the {}% confirmation threshold, continuing with the confirmed accounts",
+            config.min_confirmed
+        );
+    }
+    if !pending.is_empty() {
+        println!("Dropping {} unconfirmed accounts", pending.len());
+    }
+
+    confirmed
+}
+
+async fn account_exists(
+    http_client: &reqwest::Client,
+    gateway_url: &str,
+    wallet: &PrivateKeySigner,
+) -> bool {
+    // a failed lookup is treated as not there yet
+    matches!(
+        fetch_account(http_client, gateway_url, &wallet.address()).await,
+        Ok(Some(_))
+    )
+}
+
+/// Looks an account up on the gateway, `Ok(None)` when the network does not know it
+pub async fn fetch_account(
+    http_client: &reqwest::Client,
+    gateway_url: &str,
+    address: &Address,
+) -> Result<Option<serde_json::Value>, String> {
+    let addr = utils::to_shardus_address(&address.to_string());
+    let url = format!("{}/account/{}", gateway_url, addr);
+
+    let body = http_client
+        .get(&url)
+        .send()
+        .await
+        .map_err(|e| e.to_string())?
+        .text()
+        .await
+        .map_err(|e| e.to_string())?;
+    let resp: proxy::GetAccountResp = serde_json::from_str(&body).map_err(|e| e.to_string())?;
+    Ok(resp.account)
+}
+
+/// Balance of an account returned by `fetch_account`, in wei
+pub fn account_balance(account: &serde_json::Value) -> Option<u128> {
+    let balance = match &account["data"]["balance"] {
+        serde_json::Value::Null => &account["balance"],
+        balance => balance,
+    };
+    crate::utils::parse_wei(balance)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_threshold() {
+        let config = ConfirmationConfig::default();
+        assert_eq!(config.threshold(100), 90);
+        assert_eq!(config.threshold(7), 7);
+        assert_eq!(config.threshold(0), 0);
+
+        let all = ConfirmationConfig {
+            min_confirmed: 100.0,
+            ..Default::default()
+        };
+        assert_eq!(all.threshold(33), 33);
+
+        assert!(parse_min_confirmed("95.5").is_ok());
+        assert!(parse_min_confirmed("0").is_err());
+        assert!(parse_min_confirmed("101").is_err());
+    }
+
+    #[test]
+    fn test_account_balance() {
+        let account = serde_json::json!({
+            "id": "ab",
+            "data": { "balance": { "dataType": "bi", "value": "3635c9adc5dea00000" } }
+        });
+        assert_eq!(account_balance(&account), Some(1_000_000_000_000_000_000_000));
+
+        assert_eq!(
+            account_balance(&serde_json::json!({ "balance": "25" })),
+            Some(25)
+        );
+        assert_eq!(account_balance(&serde_json::json!({ "id": "ab" })), None);
+    }
+}
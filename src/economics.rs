@@ -0,0 +1,125 @@
+//! What the network charges, read once from the gateway's `/netconfig` so a fee
+//! changed through `change_config` is picked up by the next run
+//!
+//! - `transactionFee` is the fee of every transaction and what funding and sweeps reserve per transfer
+//! - `defaultToll` is what a message pays an account that has not set a toll of its own
+//!
+//! The other amounts in netconfig, such as the stake requirement and the toll limits, are
+//! not read. No builder sends an amount they govern: transfers carry the amount they are
+//! given, and the stake amount of `stake` comes from `--amount`.
+
+use crate::{proxy, utils};
+use std::sync::OnceLock;
+
+/// Fee in wei when the gateway does not report one, the network's initial
+/// `transactionFee` of `{"dataType": "bi", "value": "769200000000"}`
+pub const DEFAULT_TRANSACTION_FEE: u128 = 0x7692_0000_0000;
+
+static ECONOMICS: OnceLock<NetworkEconomics> = OnceLock::new();
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct NetworkEconomics {
+    /// Fee in wei of every transaction
+    pub transaction_fee: u128,
+    /// Toll in wei a message pays its recipient, the message amount
+    pub default_toll: u128,
+}
+
+impl Default for NetworkEconomics {
+    fn default() -> Self {
+        NetworkEconomics {
+            transaction_fee: DEFAULT_TRANSACTION_FEE,
+            default_toll: 0,
+        }
+    }
+}
+
+impl NetworkEconomics {
+    /// Economics of a `/netconfig` response, from `config.current`
+    pub fn from_netconfig(resp: &serde_json::Value) -> Result<Self, String> {
+        let current = &resp["config"]["current"];
+        if !current.is_object() {
+            return Err("netconfig has no config.current".to_string());
+        }
+        let wei = |key: &str| match &current[key] {
+            serde_json::Value::Null => Ok(None),
+            value => utils::parse_wei(value)
+                .map(Some)
+                .ok_or_else(|| format!("netconfig {} is not an amount: {}", key, value)),
+        };
+        Ok(NetworkEconomics {
+            transaction_fee: wei("transactionFee")?
+                .ok_or_else(|| "netconfig has no transactionFee".to_string())?,
+            default_toll: wei("defaultToll")?.unwrap_or(0),
+        })
+    }
+
+    /// Balance one message needs, its fee and toll
+    pub fn message_cost(&self) -> u128 {
+        self.transaction_fee + self.default_toll
+    }
+}
+
+/// The economics loaded at startup, the defaults until they are loaded
+pub fn current() -> NetworkEconomics {
+    ECONOMICS.get().cloned().unwrap_or_default()
+}
+
+/// Fetch the economics from the gateway, must be called before any transaction is built.
+/// Falls back to the defaults with a warning when the gateway has no usable netconfig.
+pub async fn load(gateway_url: &str) -> &'static NetworkEconomics {
+    let url = format!("{}/netconfig", gateway_url);
+    let economics = match proxy::get_request(None, &url).await {
+        Ok(resp) => NetworkEconomics::from_netconfig(&resp),
+        Err(e) => Err(format!("failed to fetch {}: {}", url, e)),
+    }
+    .unwrap_or_else(|e| {
+        eprintln!("Using default fees, {}", e);
+        NetworkEconomics::default()
+    });
+
+    let economics = ECONOMICS.get_or_init(|| economics);
+    println!(
+        "Transaction fee: {} wei, default toll: {} wei",
+        economics.transaction_fee, economics.default_toll
+    );
+    economics
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_from_netconfig() {
+        let resp = json!({
+            "config": {
+                "current": {
+                    "transactionFee": { "dataType": "bi", "value": "769200000000" },
+                    "defaultToll": { "dataType": "bi", "value": "de0b6b3a7640000" },
+                    "title": "Initial parameters",
+                }
+            }
+        });
+        let economics = NetworkEconomics::from_netconfig(&resp).unwrap();
+        assert_eq!(economics.transaction_fee, DEFAULT_TRANSACTION_FEE);
+        assert_eq!(economics.transaction_fee, 130_369_437_302_784);
+        assert_eq!(economics.default_toll, 1_000_000_000_000_000_000);
+        assert_eq!(economics.message_cost(), 1_000_130_369_437_302_784);
+
+        // no toll means messages are free to send apart from the fee
+        let resp = json!({ "config": { "current": { "transactionFee": "1000" } } });
+        assert_eq!(
+            NetworkEconomics::from_netconfig(&resp).unwrap(),
+            NetworkEconomics {
+                transaction_fee: 1000,
+                default_toll: 0
+            }
+        );
+
+        assert!(NetworkEconomics::from_netconfig(&json!({ "config": {} })).is_err());
+        let resp = json!({ "config": { "current": { "transactionFee": true } } });
+        assert!(NetworkEconomics::from_netconfig(&resp).is_err());
+    }
+}
@@ -0,0 +1,312 @@
+use crate::{
+    cli::verbose,
+    latency::{LatencyReport, SendTiming},
+    workload::TxKind,
+};
+use std::sync::Arc;
+use tokio::sync::{mpsc, Semaphore};
+use tokio::time::{Duration, Instant};
+
+/// Status requests allowed in flight at once across all tracked transactions,
+/// so tracking does not turn into a second load test against the gateway
+const MAX_CONCURRENT_POLLS: usize = 64;
+
+/// How long to wait for a transaction to be applied and how often to ask
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct FinalityConfig {
+    pub timeout: Duration,
+    pub poll_interval: Duration,
+}
+
+impl Default for FinalityConfig {
+    fn default() -> Self {
+        FinalityConfig {
+            timeout: Duration::from_secs(60),
+            poll_interval: Duration::from_millis(1000),
+        }
+    }
+}
+
+/// What the gateway currently knows about a transaction
+#[derive(Debug, Clone, PartialEq)]
+pub enum TxStatus {
+    Pending,
+    Applied,
+    Rejected(String),
+}
+
+impl TxStatus {
+    /// Reads a `/transaction/{txId}` response, anything without a receipt is still pending
+    pub fn from_response(resp: &serde_json::Value) -> TxStatus {
+        match &resp["transaction"] {
+            serde_json::Value::Object(receipt) if !receipt.is_empty() => {
+                match receipt.get("success") {
+                    Some(serde_json::Value::Bool(false)) => TxStatus::Rejected(
+                        receipt
+                            .get("reason")
+                            .and_then(|r| r.as_str())
+                            .unwrap_or("rejected")
+                            .to_string(),
+                    ),
+                    _ => TxStatus::Applied,
+                }
+            }
+            _ => TxStatus::Pending,
+        }
+    }
+}
+
+/// How tracking a transaction ended
+#[derive(Debug, Clone, PartialEq)]
+pub enum Finality {
+    Applied,
+    Rejected(String),
+    TimedOut,
+}
+
+impl Finality {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Finality::Applied => "applied",
+            Finality::Rejected(_) => "rejected",
+            Finality::TimedOut => "timed_out",
+        }
+    }
+}
+
+/// A tracked transaction reaching its final state, `timing.responded` is when
+/// the tracker saw it applied (or gave up)
+pub struct FinalityOutcome {
+    pub kind: TxKind,
+    pub tx_id: String,
+    pub finality: Finality,
+    pub timing: SendTiming,
+}
+
+impl FinalityOutcome {
+    pub fn to_json(&self, base: Instant, base_unix_ms: u128) -> serde_json::Value {
+        let reason = match &self.finality {
+            Finality::Rejected(reason) => Some(reason.as_str()),
+            _ => None,
+        };
+        serde_json::json!({
+            "type": self.kind.as_str(),
+            "txId": self.tx_id,
+            "finality": {
+                "status": self.finality.as_str(),
+                "reason": reason,
+                "timing": self.timing.to_json(base, base_unix_ms),
+            },
+        })
+    }
+}
+
+/// Polls the gateway for the receipt of every accepted transaction until it is
+/// applied, rejected or times out, outcomes arrive on the receiver returned by `new`
+#[derive(Clone)]
+pub struct FinalityTracker {
+    config: FinalityConfig,
+    http_client: reqwest::Client,
+    gateway_url: String,
+    verbosity: bool,
+    polls: Arc<Semaphore>,
+    transmitter: mpsc::UnboundedSender<FinalityOutcome>,
+}
+
+impl FinalityTracker {
+    pub fn new(
+        config: FinalityConfig,
+        http_client: reqwest::Client,
+        gateway_url: &str,
+        verbosity: bool,
+    ) -> (Self, mpsc::UnboundedReceiver<FinalityOutcome>) {
+        let (transmitter, receiver) = mpsc::unbounded_channel();
+        let tracker = FinalityTracker {
+            config,
+            http_client,
+            gateway_url: gateway_url.to_string(),
+            verbosity,
+            polls: Arc::new(Semaphore::new(MAX_CONCURRENT_POLLS)),
+            transmitter,
+        };
+        (tracker, receiver)
+    }
+
+    pub fn track(&self, kind: TxKind, tx_id: String, timing: SendTiming) {
+        let tracker = self.clone();
+        tokio::spawn(async move {
+            let (finality, observed) = tracker.poll_until_final(&tx_id, timing.sent).await;
+            let _ = tracker.transmitter.send(FinalityOutcome {
+                kind,
+                tx_id,
+                finality,
+                timing: SendTiming {
+                    responded: observed,
+                    ..timing
+                },
+            });
+        });
+    }
+
+    async fn poll_until_final(&self, tx_id: &str, sent: Instant) -> (Finality, Instant) {
+        let deadline = sent + self.config.timeout;
+        let url = format!("{}/transaction/{}", self.gateway_url, tx_id);
+
+        loop {
+            let next_poll = Instant::now() + self.config.poll_interval;
+            if next_poll > deadline {
+                tokio::time::sleep_until(deadline).await;
+                return (Finality::TimedOut, deadline);
+            }
+            tokio::time::sleep_until(next_poll).await;
+
+            let status = {
+                let _permit = self.polls.acquire().await.expect("poll semaphore closed");
+                self.fetch_status(&url).await
+            };
+            let observed = Instant::now();
+
+            match status {
+                Ok(TxStatus::Applied) => return (Finality::Applied, observed),
+                Ok(TxStatus::Rejected(reason)) => return (Finality::Rejected(reason), observed),
+                Ok(TxStatus::Pending) => {}
+                // a failed status request says nothing about the transaction, ask again
+                Err(e) => verbose(
+                    &self.verbosity,
+                    &format!("Failed to poll status of {}: {}", tx_id, e),
+                ),
+            }
+        }
+    }
+
+    async fn fetch_status(&self, url: &str) -> Result<TxStatus, Box<dyn std::error::Error>> {
+        let body = self.http_client.get(url).send().await?.text().await?;
+        let resp: serde_json::Value = serde_json::from_str(&body)?;
+        Ok(TxStatus::from_response(&resp))
+    }
+}
+
+/// Final states of the tracked transactions of one run
+#[derive(Default)]
+pub struct FinalityStats {
+    pub tracked: usize,
+    pub applied: usize,
+    pub rejected: usize,
+    pub timed_out: usize,
+    latency: LatencyReport,
+}
+
+impl FinalityStats {
+    pub fn record(&mut self, outcome: &FinalityOutcome) {
+        match outcome.finality {
+            Finality::Applied => {
+                self.applied += 1;
+                self.latency.record(outcome.kind, &outcome.timing);
+            }
+            Finality::Rejected(_) => self.rejected += 1,
+            Finality::TimedOut => self.timed_out += 1,
+        }
+    }
+
+    pub fn pending(&self) -> usize {
+        self.tracked - self.applied - self.rejected - self.timed_out
+    }
+
+    /// Share of gateway-accepted transactions that were never applied, in percent
+    pub fn never_applied_rate(&self) -> f64 {
+        if self.tracked == 0 {
+            return 0.0;
+        }
+        (self.rejected + self.timed_out) as f64 / self.tracked as f64 * 100.0
+    }
+
+    pub fn print_summary(&self) {
+        println!(
+            "Finality: {} accepted, {} applied, {} rejected, {} timed out ({:.2}% accepted but never applied)",
+            self.tracked,
+            self.applied,
+            self.rejected,
+            self.timed_out,
+            self.never_applied_rate(),
+        );
+        self.latency.print_summary("Finality (ms)");
+    }
+
+    pub fn to_json(&self) -> serde_json::Value {
+        serde_json::json!({
+            "tracked": self.tracked,
+            "applied": self.applied,
+            "rejected": self.rejected,
+            "timed_out": self.timed_out,
+            "never_applied_rate": self.never_applied_rate(),
+            "latency": self.latency.to_json(),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_status_from_response() {
+        let status = |resp: serde_json::Value| TxStatus::from_response(&resp);
+
+        assert_eq!(status(serde_json::json!({})), TxStatus::Pending);
+        assert_eq!(
+            status(serde_json::json!({ "transaction": null })),
+            TxStatus::Pending
+        );
+        assert_eq!(
+            status(serde_json::json!({ "transaction": {} })),
+            TxStatus::Pending
+        );
+        assert_eq!(
+            status(serde_json::json!({ "error": "No transaction found" })),
+            TxStatus::Pending
+        );
+        assert_eq!(
+            status(serde_json::json!({ "transaction": { "txId": "ab", "success": true } })),
+            TxStatus::Applied
+        );
+        assert_eq!(
+            status(serde_json::json!({ "transaction": { "txId": "ab" } })),
+            TxStatus::Applied
+        );
+        assert_eq!(
+            status(serde_json::json!({
+                "transaction": { "success": false, "reason": "insufficient balance" }
+            })),
+            TxStatus::Rejected("insufficient balance".to_string())
+        );
+    }
+
+    #[test]
+    fn test_never_applied_rate() {
+        let mut stats = FinalityStats {
+            tracked: 4,
+            ..Default::default()
+        };
+        let now = Instant::now();
+        let outcome = |finality| FinalityOutcome {
+            kind: TxKind::Transfer,
+            tx_id: "ab".to_string(),
+            finality,
+            timing: SendTiming {
+                intended: now,
+                sent: now,
+                responded: now + Duration::from_millis(1500),
+            },
+        };
+
+        stats.record(&outcome(Finality::Applied));
+        stats.record(&outcome(Finality::Applied));
+        stats.record(&outcome(Finality::TimedOut));
+        assert_eq!(stats.pending(), 1);
+
+        stats.record(&outcome(Finality::Rejected("nope".to_string())));
+        assert_eq!(stats.pending(), 0);
+        assert_eq!(stats.never_applied_rate(), 50.0);
+        assert_eq!(stats.to_json()["latency"]["all"]["raw"]["count"], 2);
+    }
+}
@@ -0,0 +1,685 @@
+use crate::{
+    account_store::{self, NetworkKey, OnChainStatus},
+    cli::verbose,
+    crypto::{self, ShardusCrypto},
+    economics, load_injector,
+    transactions::{self, LiberdusTransactions},
+    utils,
+};
+use alloy::{primitives::Address, signers::local::PrivateKeySigner};
+use std::collections::HashMap;
+use std::sync::Arc;
+use tokio::sync::Semaphore;
+use tokio::task::JoinSet;
+use tokio::time::{Duration, Instant};
+
+/// Environment variable the funding key may be given in instead of on the command line
+pub const FUNDING_KEY_ENV: &str = "FUNDING_KEY";
+
+/// Transfers injected at once within a round
+const MAX_CONCURRENT_TRANSFERS: usize = 64;
+
+/// Lookups of an account's balance before it is skipped as unknown
+const BALANCE_LOOKUP_ATTEMPTS: usize = 3;
+
+/// Who gets funded to what, and how the fan-out is shaped
+#[derive(Debug, Clone)]
+pub struct FundingConfig {
+    pub funder: PrivateKeySigner,
+    /// Balance in wei every pool account should hold before load starts
+    pub target_balance: u128,
+    /// Accounts every funded account passes funds on to in the next round
+    pub fanout: usize,
+    /// Reserved for every forward, the network's transaction fee by default
+    pub transfer_fee: u128,
+    /// How long to wait for one round of transfers to land
+    pub round_timeout: Duration,
+    pub poll_interval: Duration,
+}
+
+impl FundingConfig {
+    pub fn new(funder: PrivateKeySigner, target_balance: u128) -> Self {
+        FundingConfig {
+            funder,
+            target_balance,
+            fanout: 8,
+            transfer_fee: economics::current().transaction_fee,
+            round_timeout: Duration::from_secs(60),
+            poll_interval: Duration::from_millis(1000),
+        }
+    }
+}
+
+/// Parses a hex private key, `0x` optional
+pub fn parse_funding_key(s: &str) -> Result<PrivateKeySigner, String> {
+    s.trim()
+        .trim_start_matches("0x")
+        .parse::<PrivateKeySigner>()
+        .map_err(|_| "funding key is not a hex private key".to_string())
+}
+
+/// The funding key from `FUNDING_KEY` (environment or .env)
+pub fn funding_key_from_env() -> Option<Result<PrivateKeySigner, String>> {
+    dotenvy::dotenv().ok();
+    std::env::var(FUNDING_KEY_ENV)
+        .ok()
+        .filter(|key| !key.is_empty())
+        .map(|key| parse_funding_key(&key))
+}
+
+/// One transfer of the fan-out. Node 0 is the funder, node `i` is the `i`th
+/// account that needs funds.
+#[derive(Debug, Clone, PartialEq)]
+struct PlannedTransfer {
+    from: usize,
+    to: usize,
+    amount: u128,
+}
+
+/// Lay the accounts out as a `fanout`-ary tree under the funder and return its
+/// transfers round by round. Every account receives its own top-up plus
+/// everything its subtree needs, including the fees of its own forwards.
+fn plan_fan_out(needs: &[u128], fanout: usize, transfer_fee: u128) -> Vec<Vec<PlannedTransfer>> {
+    let nodes = needs.len() + 1;
+    let children = |node: usize| (node * fanout + 1)..((node + 1) * fanout + 1).min(nodes);
+
+    // children come after their parent, so one backwards pass sums every subtree
+    let mut subtree = vec![0u128; nodes];
+    for node in (1..nodes).rev() {
+        let forwarded: u128 = children(node).map(|child| subtree[child]).sum();
+        subtree[node] = needs[node - 1] + forwarded + transfer_fee * children(node).len() as u128;
+    }
+
+    let subtree = &subtree;
+    let mut rounds: Vec<Vec<PlannedTransfer>> = Vec::new();
+    let mut senders = vec![0];
+    while !senders.is_empty() {
+        let round: Vec<PlannedTransfer> = senders
+            .iter()
+            .flat_map(|&from| {
+                children(from).map(move |to| PlannedTransfer {
+                    from,
+                    to,
+                    amount: subtree[to],
+                })
+            })
+            .collect();
+        senders = round.iter().map(|transfer| transfer.to).collect();
+        if !round.is_empty() {
+            rounds.push(round);
+        }
+    }
+    rounds
+}
+
+/// The wallets below the target balance with the top-up each one needs. Wallets
+/// missing from `balances` could not be looked up and are left out rather than
+/// topped up from zero. The funder may be part of the pool, it is never a target
+/// of its own transfers.
+fn top_ups(
+    wallets: &[PrivateKeySigner],
+    balances: &HashMap<Address, u128>,
+    config: &FundingConfig,
+) -> Vec<(PrivateKeySigner, u128)> {
+    wallets
+        .iter()
+        .filter(|wallet| wallet.address() != config.funder.address())
+        .filter_map(|wallet| {
+            let balance = *balances.get(&wallet.address())?;
+            (balance < config.target_balance)
+                .then(|| (wallet.clone(), config.target_balance - balance))
+        })
+        .collect()
+}
+
+/// Balances of `wallets`, accounts the network does not know hold nothing.
+/// Failed lookups are tried again a few times, the accounts that never
+/// answered are returned on their own.
+async fn look_up_balances(
+    wallets: Vec<PrivateKeySigner>,
+    gateway_url: &str,
+    config: &FundingConfig,
+) -> (HashMap<Address, u128>, Vec<PrivateKeySigner>) {
+    let mut balances = HashMap::new();
+    let mut unknown = wallets;
+    for attempt in 0..BALANCE_LOOKUP_ATTEMPTS {
+        if attempt > 0 {
+            tokio::time::sleep(config.poll_interval).await;
+        }
+        let mut failed = Vec::new();
+        for (wallet, status) in account_store::check_accounts(unknown, gateway_url).await {
+            match status {
+                OnChainStatus::Found { balance } => {
+                    balances.insert(wallet.address(), balance);
+                }
+                OnChainStatus::Missing => {
+                    balances.insert(wallet.address(), 0);
+                }
+                OnChainStatus::Unreachable(_) => failed.push(wallet),
+            }
+        }
+        unknown = failed;
+        if unknown.is_empty() {
+            break;
+        }
+    }
+    (balances, unknown)
+}
+
+/// Top every wallet up to `target_balance` from the funder through a fan-out
+/// tree, then check the balances. Returns the wallets holding the target balance.
+/// Fails before the first transfer when the funder cannot pay for the whole tree.
+pub async fn fund_accounts(
+    wallets: Vec<PrivateKeySigner>,
+    gateway_url: &str,
+    shardus_crypto: Arc<ShardusCrypto>,
+    config: &FundingConfig,
+    verbosity: &bool,
+) -> Result<Vec<PrivateKeySigner>, String> {
+    let start = Instant::now();
+    let gateway = gateway_url.to_string();
+
+    // only accounts below the target take part, topped up by the difference
+    let (balances, unknown) = look_up_balances(wallets.clone(), gateway_url, config).await;
+    if !unknown.is_empty() {
+        eprintln!(
+            "Skipping {} accounts whose balance could not be looked up",
+            unknown.len()
+        );
+        for wallet in &unknown {
+            verbose(
+                verbosity,
+                &format!("Balance of {} unknown, not funded", wallet.address()),
+            );
+        }
+    }
+    let (needy, needs): (Vec<PrivateKeySigner>, Vec<u128>) =
+        top_ups(&wallets, &balances, config).into_iter().unzip();
+
+    let rounds = plan_fan_out(&needs, config.fanout, config.transfer_fee);
+    let required: u128 = rounds
+        .first()
+        .map(|round| round.iter().map(|t| t.amount + config.transfer_fee).sum())
+        .unwrap_or(0);
+    if required > 0 {
+        let (funder_balance, _) =
+            look_up_balances(vec![config.funder.clone()], gateway_url, config).await;
+        let available = funder_balance
+            .get(&config.funder.address())
+            .copied()
+            .ok_or_else(|| {
+                format!(
+                    "could not look up the balance of the funder {}",
+                    config.funder.address()
+                )
+            })?;
+        if available < required {
+            return Err(funder_shortfall(&config.funder, available, required));
+        }
+    }
+    println!(
+        "Funding {} of {} accounts to {} wei in {} rounds, the funder {} sends {} wei",
+        needy.len(),
+        wallets.len(),
+        config.target_balance,
+        rounds.len(),
+        config.funder.address(),
+        required
+    );
+
+    // node 0 is the funder, node i the ith needy account
+    let mut nodes = vec![config.funder.clone()];
+    nodes.extend(needy.iter().cloned());
+    let mut expected: Vec<u128> = vec![0];
+    expected.extend(needy.iter().map(|w| balances[&w.address()]));
+    let mut failed = vec![false; nodes.len()];
+
+    let http_client = reqwest::Client::builder()
+        .danger_accept_invalid_certs(true)
+        .build()
+        .unwrap();
+    let transfers = Arc::new(Semaphore::new(MAX_CONCURRENT_TRANSFERS));
+
+    for (round_number, round) in rounds.iter().enumerate() {
+        let mut injections = JoinSet::new();
+        for transfer in round {
+            // a sender that never got its funds has nothing to pass on
+            if failed[transfer.from] {
+                failed[transfer.to] = true;
+                continue;
+            }
+            let tx = transactions::build_transfer_transaction(
+                &shardus_crypto,
+                &nodes[transfer.from],
+                &nodes[transfer.to].address(),
+                transfer.amount,
+            );
+            let http_client = http_client.clone();
+            let transfers = Arc::clone(&transfers);
+            let gateway = gateway.clone();
+            let verbosity = *verbosity;
+            let to = transfer.to;
+            injections.spawn(async move {
+                let _permit = transfers
+                    .acquire()
+                    .await
+                    .expect("transfer semaphore closed");
+                let resp = transactions::inject_transaction(
+                    http_client,
+                    &LiberdusTransactions::Transfer(tx),
+                    &gateway,
+                    &verbosity,
+                )
+                .await
+                .map_err(|e| e.to_string());
+                (to, resp)
+            });
+        }
+
+        let mut landing = Vec::new();
+        while let Some(injection) = injections.join_next().await {
+            let (to, resp) = injection.expect("funding transfer panicked");
+            match resp {
+                Ok(resp) if resp.success => landing.push(to),
+                Ok(resp) => {
+                    verbose(
+                        verbosity,
+                        &format!(
+                            "Funding transfer to {} rejected: {}",
+                            nodes[to].address(),
+                            resp.reason
+                        ),
+                    );
+                    failed[to] = true;
+                }
+                Err(e) => {
+                    verbose(
+                        verbosity,
+                        &format!("Funding transfer to {} failed: {}", nodes[to].address(), e),
+                    );
+                    failed[to] = true;
+                }
+            }
+        }
+        for transfer in round.iter().filter(|t| landing.contains(&t.to)) {
+            expected[transfer.to] += transfer.amount;
+        }
+
+        let arrived = wait_for_balances(&nodes, &expected, landing, gateway_url, config).await;
+        for (node, arrived) in arrived {
+            if !arrived {
+                failed[node] = true;
+            }
+        }
+        println!(
+            "Funding round {}/{}: {} transfers, {} accounts without funds so far",
+            round_number + 1,
+            rounds.len(),
+            round.len(),
+            failed.iter().filter(|f| **f).count()
+        );
+    }
+
+    // the tree only says what was sent, the network says what arrived
+    let mut funded = Vec::new();
+    let mut short = 0;
+    for (wallet, status) in account_store::check_accounts(wallets, gateway_url).await {
+        match status {
+            OnChainStatus::Found { balance } if balance >= config.target_balance => {
+                funded.push(wallet)
+            }
+            _ => short += 1,
+        }
+    }
+    println!(
+        "Funded {} accounts to at least {} wei in {:.1}s, {} below target",
+        funded.len(),
+        config.target_balance,
+        start.elapsed().as_secs_f64(),
+        short
+    );
+    Ok(funded)
+}
+
+fn funder_shortfall(funder: &PrivateKeySigner, available: u128, required: u128) -> String {
+    format!(
+        "the funder {} holds {} wei but the fan-out needs {} wei, {} wei short",
+        funder.address(),
+        available,
+        required,
+        required - available
+    )
+}
+
+/// Poll the receivers of a round until their balance reaches what was sent
+/// to them, returns whether it did for every receiver
+async fn wait_for_balances(
+    nodes: &[PrivateKeySigner],
+    expected: &[u128],
+    receivers: Vec<usize>,
+    gateway_url: &str,
+    config: &FundingConfig,
+) -> Vec<(usize, bool)> {
+    let deadline = Instant::now() + config.round_timeout;
+    let mut pending = receivers;
+    let mut results = Vec::new();
+
+    loop {
+        let wallets = pending.iter().map(|&node| nodes[node].clone()).collect();
+        let statuses: HashMap<_, _> = account_store::check_accounts(wallets, gateway_url)
+            .await
+            .into_iter()
+            .map(|(wallet, status)| (wallet.address(), status))
+            .collect();
+
+        pending.retain(|&node| {
+            let arrived = matches!(
+                statuses.get(&nodes[node].address()),
+                Some(OnChainStatus::Found { balance }) if *balance >= expected[node]
+            );
+            if arrived {
+                results.push((node, true));
+            }
+            !arrived
+        });
+
+        let next_poll = Instant::now() + config.poll_interval;
+        if pending.is_empty() || next_poll > deadline {
+            break;
+        }
+        tokio::time::sleep_until(next_poll).await;
+    }
+
+    results.extend(pending.into_iter().map(|node| (node, false)));
+    results
+}
+
+/// Where leftover balances go and which ones are worth moving
+#[derive(Debug, Clone)]
+pub struct SweepConfig {
+    pub collector: Address,
+    /// Balances that would leave at most this much after the fee stay where they are
+    pub dust: u128,
+    pub transfer_fee: u128,
+    /// Sweep transfers in flight at once
+    pub concurrency: usize,
+    pub dry_run: bool,
+}
+
+/// What a sweep transfer moves out of `balance`, everything but the fee,
+/// or nothing when that is dust
+fn sweep_amount(balance: u128, transfer_fee: u128, dust: u128) -> Option<u128> {
+    balance
+        .checked_sub(transfer_fee)
+        .filter(|amount| *amount > dust)
+}
+
+/// Send the balances of the idle stored accounts of `network` to the collector
+/// and write a report of every account to `./artifacts/sweep_<millis>.json`.
+/// The swept accounts are leased for the whole sweep, so no run sends from a
+/// wallet that is being drained, and released at the end, also on Ctrl-C.
+pub async fn sweep_accounts(
+    network: &NetworkKey,
+    config: &SweepConfig,
+    verbosity: &bool,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let (wallets, in_use) = account_store::lease_idle_accounts(network)?;
+    let result = tokio::select! {
+        result = sweep_leased(wallets, in_use, network, config, verbosity) => result,
+        _ = tokio::signal::ctrl_c() => {
+            println!("\nInterrupted");
+            load_injector::release_account_leases(verbosity);
+            std::process::exit(130);
+        }
+    };
+    load_injector::release_account_leases(verbosity);
+    result
+}
+
+async fn sweep_leased(
+    mut wallets: Vec<PrivateKeySigner>,
+    in_use: usize,
+    network: &NetworkKey,
+    config: &SweepConfig,
+    verbosity: &bool,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let shardus_crypto = Arc::new(crypto::ShardusCrypto::new(
+        "69fa4195670576c0160d660c3be36556ff8d504725be8a59b5a96509e0c994bc",
+    ));
+    wallets.retain(|wallet| wallet.address() != config.collector);
+    println!(
+        "Checking the balances of {} accounts of {}, {} in use are left alone",
+        wallets.len(),
+        network,
+        in_use
+    );
+
+    let mut report = Vec::new();
+    let mut sweeps = Vec::new();
+    let (mut dust, mut missing, mut unreachable) = (0, 0, 0);
+    for (wallet, status) in account_store::check_accounts(wallets, &network.gateway_url).await {
+        let address = wallet.address().to_string();
+        match status {
+            OnChainStatus::Found { balance } => {
+                match sweep_amount(balance, config.transfer_fee, config.dust) {
+                    Some(amount) => sweeps.push((wallet, balance, amount)),
+                    None => {
+                        dust += 1;
+                        report.push(serde_json::json!({
+                            "address": address,
+                            "balance": balance.to_string(),
+                            "status": "dust",
+                        }));
+                    }
+                }
+            }
+            OnChainStatus::Missing => {
+                missing += 1;
+                report.push(serde_json::json!({ "address": address, "status": "missing" }));
+            }
+            OnChainStatus::Unreachable(e) => {
+                verbose(verbosity, &format!("Failed to look up {}: {}", address, e));
+                unreachable += 1;
+                report.push(serde_json::json!({
+                    "address": address,
+                    "status": "unreachable",
+                    "reason": e,
+                }));
+            }
+        }
+    }
+
+    let planned: u128 = sweeps.iter().map(|(_, _, amount)| amount).sum();
+    let planned_accounts = sweeps.len();
+    let (mut swept, mut swept_accounts, mut failed) = (0u128, 0, 0);
+    if config.dry_run {
+        for (wallet, balance, amount) in &sweeps {
+            verbose(
+                verbosity,
+                &format!(
+                    "Would sweep {} of {} wei from {}",
+                    amount,
+                    balance,
+                    wallet.address()
+                ),
+            );
+            report.push(serde_json::json!({
+                "address": wallet.address().to_string(),
+                "balance": balance.to_string(),
+                "amount": amount.to_string(),
+                "status": "planned",
+            }));
+        }
+    } else {
+        let http_client = reqwest::Client::builder()
+            .danger_accept_invalid_certs(true)
+            .build()
+            .unwrap();
+        let transfers = Arc::new(Semaphore::new(config.concurrency));
+        let mut injections = JoinSet::new();
+        for (wallet, balance, amount) in sweeps {
+            let tx = transactions::build_transfer_transaction(
+                &shardus_crypto,
+                &wallet,
+                &config.collector,
+                amount,
+            );
+            let http_client = http_client.clone();
+            let transfers = Arc::clone(&transfers);
+            let gateway = network.gateway_url.clone();
+            let verbosity = *verbosity;
+            injections.spawn(async move {
+                let _permit = transfers.acquire().await.expect("sweep semaphore closed");
+                let resp = transactions::inject_transaction(
+                    http_client,
+                    &LiberdusTransactions::Transfer(tx),
+                    &gateway,
+                    &verbosity,
+                )
+                .await
+                .map_err(|e| e.to_string());
+                (wallet.address(), balance, amount, resp)
+            });
+        }
+
+        while let Some(injection) = injections.join_next().await {
+            let (address, balance, amount, resp) = injection.expect("sweep transfer panicked");
+            let mut entry = serde_json::json!({
+                "address": address.to_string(),
+                "balance": balance.to_string(),
+                "amount": amount.to_string(),
+            });
+            match resp {
+                Ok(resp) if resp.success => {
+                    swept += amount;
+                    swept_accounts += 1;
+                    entry["status"] = "swept".into();
+                    entry["tx_id"] = resp.txId.into();
+                }
+                Ok(resp) => {
+                    verbose(
+                        verbosity,
+                        &format!("Sweep of {} rejected: {}", address, resp.reason),
+                    );
+                    failed += 1;
+                    entry["status"] = "rejected".into();
+                    entry["reason"] = resp.reason.into();
+                }
+                Err(e) => {
+                    verbose(verbosity, &format!("Sweep of {} failed: {}", address, e));
+                    failed += 1;
+                    entry["status"] = "failed".into();
+                    entry["reason"] = e.into();
+                }
+            }
+            report.push(entry);
+        }
+    }
+
+    let report_path = format!("./artifacts/sweep_{}.json", now_millis());
+    utils::write_json_to_file(
+        &report_path,
+        &serde_json::json!({
+            "network": network.to_string(),
+            "collector": config.collector.to_string(),
+            "dust": config.dust.to_string(),
+            "transfer_fee": config.transfer_fee.to_string(),
+            "dry_run": config.dry_run,
+            "swept": swept.to_string(),
+            "accounts": report,
+        }),
+    )?;
+
+    if config.dry_run {
+        println!(
+            "Would sweep {} wei from {} accounts to {}",
+            planned, planned_accounts, config.collector
+        );
+    } else {
+        println!(
+            "Swept {} wei from {} accounts to {}, {} transfers failed",
+            swept, swept_accounts, config.collector, failed
+        );
+    }
+    println!(
+        "{} accounts held dust, {} missing, {} unreachable. Report: {}",
+        dust, missing, unreachable, report_path
+    );
+    Ok(())
+}
+
+fn now_millis() -> u128 {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .unwrap()
+        .as_millis()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_plan_fan_out() {
+        // funder -> 1, 2; 1 -> 3, 4; 2 -> 5
+        let rounds = plan_fan_out(&[10, 10, 10, 10, 10], 2, 1);
+        assert_eq!(rounds.len(), 2);
+        assert_eq!(
+            rounds[0],
+            vec![
+                PlannedTransfer {
+                    from: 0,
+                    to: 1,
+                    amount: 10 + 20 + 2
+                },
+                PlannedTransfer {
+                    from: 0,
+                    to: 2,
+                    amount: 10 + 10 + 1
+                },
+            ]
+        );
+        assert_eq!(rounds[1].len(), 3);
+        assert!(rounds[1].iter().all(|t| t.amount == 10));
+        assert_eq!(rounds[1][2].from, 2);
+
+        // depth grows with the log of the pool
+        assert_eq!(plan_fan_out(&vec![1; 1000], 8, 0).len(), 4);
+        assert!(plan_fan_out(&[], 8, 0).is_empty());
+    }
+
+    #[test]
+    fn test_top_ups() {
+        let funder = PrivateKeySigner::random();
+        let poor = PrivateKeySigner::random();
+        let rich = PrivateKeySigner::random();
+        let unknown = PrivateKeySigner::random();
+        let config = FundingConfig::new(funder.clone(), 100);
+        let balances = HashMap::from([
+            (funder.address(), 0),
+            (poor.address(), 30),
+            (rich.address(), 100),
+        ]);
+
+        // the funder sits in the pool below the target, it still does not fund itself,
+        // and an account whose balance is unknown is not topped up from zero
+        let wallets = [funder.clone(), poor.clone(), rich, unknown];
+        let top_ups: Vec<_> = top_ups(&wallets, &balances, &config)
+            .into_iter()
+            .map(|(wallet, amount)| (wallet.address(), amount))
+            .collect();
+        assert_eq!(top_ups, vec![(poor.address(), 70)]);
+
+        assert!(funder_shortfall(&funder, 40, 100).ends_with("needs 100 wei, 60 wei short"));
+    }
+
+    #[test]
+    fn test_sweep_amount() {
+        // the fee comes out of the balance, what is left must beat the dust threshold
+        assert_eq!(sweep_amount(1_000, 100, 0), Some(900));
+        assert_eq!(sweep_amount(1_000, 100, 899), Some(900));
+        assert_eq!(sweep_amount(1_000, 100, 900), None);
+        assert_eq!(sweep_amount(50, 100, 0), None);
+        assert_eq!(sweep_amount(100, 100, 0), None);
+    }
+}
@@ -0,0 +1,135 @@
+use alloy::signers::{
+    k256::ecdsa::SigningKey,
+    local::{
+        coins_bip39::{English, Mnemonic},
+        PrivateKeySigner,
+    },
+};
+
+/// Parent of the derived accounts, the index is the last path component
+const DERIVATION_PATH_PREFIX: &str = "m/44'/60'/0'/0";
+
+/// Indices from 2^31 up are hardened, the path has room for 2^31 accounts
+const INDEX_LIMIT: u32 = 1 << 31;
+
+/// Accounts derived from a BIP-39 mnemonic at the standard Ethereum path
+/// `m/44'/60'/0'/0/{index}`, so the same phrase and indices always give the same pool
+#[derive(Clone)]
+pub struct HdAccounts {
+    phrase: String,
+    /// Index of the first account of the pool
+    pub start: u32,
+}
+
+// keep the phrase out of logged parameters
+impl std::fmt::Debug for HdAccounts {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        f.debug_struct("HdAccounts")
+            .field("phrase", &"<redacted>")
+            .field("start", &self.start)
+            .finish()
+    }
+}
+
+impl HdAccounts {
+    /// Checks the phrase up front, a typo should fail before anything is registered
+    pub fn new(phrase: &str, start: u32) -> Result<Self, String> {
+        let accounts = HdAccounts {
+            phrase: phrase.split_whitespace().collect::<Vec<_>>().join(" "),
+            start,
+        };
+        accounts.derive(1)?;
+        Ok(accounts)
+    }
+
+    /// Fails unless indices `start..start + count` fit the derivation path
+    pub fn check_range(&self, count: usize) -> Result<(), String> {
+        u32::try_from(count)
+            .ok()
+            .and_then(|count| self.start.checked_add(count))
+            .filter(|end| *end <= INDEX_LIMIT)
+            .map(|_| ())
+            .ok_or_else(|| {
+                format!(
+                    "{} accounts from index {} go past the last derivation index {}",
+                    count,
+                    self.start,
+                    INDEX_LIMIT - 1
+                )
+            })
+    }
+
+    /// The pool that follows the first `count` accounts of this one, `count` must have
+    /// passed `check_range`
+    pub fn skip(&self, count: usize) -> HdAccounts {
+        HdAccounts {
+            phrase: self.phrase.clone(),
+            start: self.start + count as u32,
+        }
+    }
+
+    /// Indices `start..start + count`
+    pub fn indices(&self, count: usize) -> std::ops::Range<u32> {
+        self.start..self.start + count as u32
+    }
+
+    /// The `count` accounts starting at `start`, in index order
+    pub fn derive(&self, count: usize) -> Result<Vec<PrivateKeySigner>, String> {
+        self.check_range(count)?;
+        let mnemonic = Mnemonic::<English>::new_from_phrase(&self.phrase)
+            .map_err(|e| format!("Invalid mnemonic: {}", e))?;
+        // stretching the phrase into a seed is the slow part, do it once per pool
+        let parent = mnemonic
+            .master_key(None)
+            .and_then(|master| Ok(master.derive_path(DERIVATION_PATH_PREFIX)?))
+            .map_err(|e| format!("Failed to derive the mnemonic's master key: {}", e))?;
+
+        self.indices(count)
+            .map(|index| {
+                let child = parent
+                    .derive_path(format!("m/{}", index).as_str())
+                    .map_err(|e| format!("Failed to derive account {}: {}", index, e))?;
+                let key: &SigningKey = child.as_ref();
+                PrivateKeySigner::from_slice(&key.to_bytes())
+                    .map_err(|e| format!("Failed to derive account {}: {}", index, e))
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PHRASE: &str = "test test test test test test test test test test test junk";
+
+    #[test]
+    fn test_derive_known_accounts() {
+        let accounts = HdAccounts::new(PHRASE, 0).unwrap();
+        let derived = accounts.derive(2).unwrap();
+
+        // the well-known development accounts of this phrase
+        assert_eq!(
+            derived[0].address().to_string(),
+            "0xf39Fd6e51aad88F6F4ce6aB8827279cffFb92266"
+        );
+        assert_eq!(
+            derived[1].address().to_string(),
+            "0x70997970C51812dc3A010C7d01b50e0d17dc79C8"
+        );
+
+        let next = accounts.skip(1).derive(1).unwrap();
+        assert_eq!(next[0].address(), derived[1].address());
+        assert_eq!(accounts.indices(3), 0..3);
+
+        assert!(HdAccounts::new("not a mnemonic", 0).is_err());
+
+        // the last index is 2^31 - 1, anything past it is rejected rather than wrapped
+        let near_end = HdAccounts::new(PHRASE, INDEX_LIMIT - 2).unwrap();
+        assert!(near_end.check_range(2).is_ok());
+        assert!(near_end.check_range(3).is_err());
+        assert!(near_end.derive(3).is_err());
+        assert!(HdAccounts::new(PHRASE, u32::MAX).is_err());
+        assert!(!format!("{:?}", accounts).contains("junk"));
+    }
+}
@@ -0,0 +1,193 @@
+use serde::{Deserialize, Serialize};
+use sodiumoxide::crypto::{pwhash::argon2id13, secretbox};
+use std::sync::{Mutex, OnceLock};
+
+/// Environment variable holding the account store passphrase, prompted for when unset
+pub const PASSPHRASE_ENV: &str = "ACCOUNTS_PASSPHRASE";
+
+const KEYSTORE_VERSION: u32 = 1;
+const KDF: &str = "argon2id13";
+
+static PASSPHRASE: OnceLock<String> = OnceLock::new();
+/// Key derived for the store last read or written, deriving takes a
+/// noticeable moment and the store is rewritten several times per run
+static DERIVED_KEY: Mutex<Option<(String, String, secretbox::Key)>> = Mutex::new(None);
+
+/// An encrypted account store as written to disk, the plaintext is the JSON of
+/// the store sealed with a key derived from the passphrase
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct Keystore {
+    pub version: u32,
+    pub kdf: String,
+    pub opslimit: usize,
+    pub memlimit: usize,
+    pub salt: String,
+    pub nonce: String,
+    pub ciphertext: String,
+}
+
+#[derive(Serialize, Deserialize)]
+struct KeystoreFile {
+    keystore: Keystore,
+}
+
+/// Whether `content` of a store file is an encrypted keystore
+pub fn is_encrypted(content: &str) -> bool {
+    serde_json::from_str::<serde_json::Value>(content)
+        .map(|value| value.get("keystore").is_some())
+        .unwrap_or(false)
+}
+
+/// Whether a passphrase was given through the environment, new stores are
+/// encrypted when it is
+pub fn passphrase_configured() -> bool {
+    PASSPHRASE.get().is_some() || env_passphrase().is_some()
+}
+
+/// The store passphrase from the environment, or asked for once on the terminal
+pub fn passphrase() -> Result<&'static str, Box<dyn std::error::Error>> {
+    if let Some(passphrase) = PASSPHRASE.get() {
+        return Ok(passphrase);
+    }
+    let passphrase = match env_passphrase() {
+        Some(passphrase) => passphrase,
+        None => rpassword::prompt_password("Account store passphrase: ").map_err(|e| {
+            format!(
+                "the account store is encrypted, set {} or run from a terminal ({})",
+                PASSPHRASE_ENV, e
+            )
+        })?,
+    };
+    if passphrase.is_empty() {
+        return Err("empty account store passphrase".into());
+    }
+    Ok(PASSPHRASE.get_or_init(|| passphrase))
+}
+
+fn env_passphrase() -> Option<String> {
+    // like NETWORK_ID, the passphrase may come from .env
+    dotenvy::dotenv().ok();
+    std::env::var(PASSPHRASE_ENV)
+        .ok()
+        .filter(|passphrase| !passphrase.is_empty())
+}
+
+/// Seal `plaintext` under `passphrase`, reusing `salt` when the store already has one
+pub fn seal(
+    plaintext: &[u8],
+    passphrase: &str,
+    salt: Option<&str>,
+) -> Result<String, Box<dyn std::error::Error>> {
+    sodiumoxide::init().map_err(|_| "Failed to initialize sodiumoxide")?;
+
+    let salt = match salt {
+        Some(salt) => salt.to_string(),
+        None => hex::encode(argon2id13::gen_salt().0),
+    };
+    let key = derive_key(
+        passphrase,
+        &salt,
+        argon2id13::OPSLIMIT_INTERACTIVE.0,
+        argon2id13::MEMLIMIT_INTERACTIVE.0,
+    )?;
+    let nonce = secretbox::gen_nonce();
+    let ciphertext = secretbox::seal(plaintext, &nonce, &key);
+
+    let file = KeystoreFile {
+        keystore: Keystore {
+            version: KEYSTORE_VERSION,
+            kdf: KDF.to_string(),
+            opslimit: argon2id13::OPSLIMIT_INTERACTIVE.0,
+            memlimit: argon2id13::MEMLIMIT_INTERACTIVE.0,
+            salt,
+            nonce: hex::encode(nonce.0),
+            ciphertext: hex::encode(ciphertext),
+        },
+    };
+    Ok(serde_json::to_string_pretty(&file)?)
+}
+
+/// Open a keystore file, returns the plaintext and the salt to seal it with again
+pub fn open(
+    content: &str,
+    passphrase: &str,
+) -> Result<(Vec<u8>, String), Box<dyn std::error::Error>> {
+    sodiumoxide::init().map_err(|_| "Failed to initialize sodiumoxide")?;
+
+    let keystore = serde_json::from_str::<KeystoreFile>(content)?.keystore;
+    if keystore.version != KEYSTORE_VERSION || keystore.kdf != KDF {
+        return Err(format!(
+            "unsupported keystore version {} ({})",
+            keystore.version, keystore.kdf
+        )
+        .into());
+    }
+
+    let key = derive_key(
+        passphrase,
+        &keystore.salt,
+        keystore.opslimit,
+        keystore.memlimit,
+    )?;
+    let nonce = secretbox::Nonce::from_slice(&hex::decode(&keystore.nonce)?)
+        .ok_or("invalid keystore nonce")?;
+    let plaintext = secretbox::open(&hex::decode(&keystore.ciphertext)?, &nonce, &key)
+        .map_err(|_| "wrong passphrase or corrupted account store")?;
+    Ok((plaintext, keystore.salt))
+}
+
+/// Salt of a keystore file, so writes keep the key the store was opened with
+pub fn salt_of(content: &str) -> Option<String> {
+    serde_json::from_str::<KeystoreFile>(content)
+        .ok()
+        .map(|file| file.keystore.salt)
+}
+
+fn derive_key(
+    passphrase: &str,
+    salt: &str,
+    opslimit: usize,
+    memlimit: usize,
+) -> Result<secretbox::Key, Box<dyn std::error::Error>> {
+    let mut cached = DERIVED_KEY.lock().unwrap();
+    if let Some((cached_salt, cached_passphrase, key)) = cached.as_ref() {
+        if cached_salt == salt && cached_passphrase == passphrase {
+            return Ok(key.clone());
+        }
+    }
+
+    let salt_bytes =
+        argon2id13::Salt::from_slice(&hex::decode(salt)?).ok_or("invalid keystore salt")?;
+    let mut key = secretbox::Key([0; secretbox::KEYBYTES]);
+    argon2id13::derive_key(
+        &mut key.0,
+        passphrase.as_bytes(),
+        &salt_bytes,
+        argon2id13::OpsLimit(opslimit),
+        argon2id13::MemLimit(memlimit),
+    )
+    .map_err(|_| "Failed to derive the account store key")?;
+
+    *cached = Some((salt.to_string(), passphrase.to_string(), key.clone()));
+    Ok(key)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_seal_and_open() {
+        let plaintext = br#"{"accounts":[],"last_updated":0}"#;
+        let sealed = seal(plaintext, "correct horse", None).unwrap();
+        assert!(is_encrypted(&sealed));
+        assert!(!sealed.contains("accounts"));
+
+        let (opened, salt) = open(&sealed, "correct horse").unwrap();
+        assert_eq!(opened, plaintext);
+        assert_eq!(Some(salt), salt_of(&sealed));
+
+        assert!(open(&sealed, "battery staple").is_err());
+        assert!(!is_encrypted(std::str::from_utf8(plaintext).unwrap()));
+    }
+}
@@ -0,0 +1,259 @@
+use crate::workload::TxKind;
+use hdrhistogram::Histogram;
+use std::collections::BTreeMap;
+use tokio::time::{Duration, Instant};
+
+/// Highest latency the histograms track, longer samples are clamped to it
+const MAX_LATENCY_MICROS: u64 = 60 * 60 * 1_000_000;
+
+/// When a transaction was meant to go out, actually went out and got its gateway response
+#[derive(Debug, Clone, Copy)]
+pub struct SendTiming {
+    pub intended: Instant,
+    pub sent: Instant,
+    pub responded: Instant,
+}
+
+impl SendTiming {
+    /// Gateway round-trip as seen by the request itself
+    pub fn raw(&self) -> Duration {
+        self.responded.saturating_duration_since(self.sent)
+    }
+
+    /// Round-trip measured from the intended send time, so time the generator
+    /// spent behind schedule counts against the network instead of vanishing
+    pub fn corrected(&self) -> Duration {
+        self.responded.saturating_duration_since(self.intended)
+    }
+
+    /// Timestamps as unix milliseconds for the run artifacts
+    pub fn to_json(self, base: Instant, base_unix_ms: u128) -> serde_json::Value {
+        let unix_ms =
+            |instant: Instant| base_unix_ms + instant.saturating_duration_since(base).as_millis();
+        serde_json::json!({
+            "intended_at": unix_ms(self.intended),
+            "sent_at": unix_ms(self.sent),
+            "responded_at": unix_ms(self.responded),
+            "latency_ms": self.raw().as_secs_f64() * 1000.0,
+            "corrected_latency_ms": self.corrected().as_secs_f64() * 1000.0,
+        })
+    }
+}
+
+/// Percentiles reported in the summaries and the histogram artifact
+const PERCENTILES: [(&str, f64); 4] = [
+    ("p50", 0.50),
+    ("p90", 0.90),
+    ("p99", 0.99),
+    ("p99.9", 0.999),
+];
+
+/// Raw and coordinated-omission-corrected latency of gateway inject round-trips
+pub struct LatencyStats {
+    raw: Histogram<u64>,
+    corrected: Histogram<u64>,
+}
+
+impl LatencyStats {
+    pub fn new() -> Self {
+        LatencyStats {
+            raw: new_histogram(),
+            corrected: new_histogram(),
+        }
+    }
+
+    pub fn record(&mut self, timing: &SendTiming) {
+        self.raw.saturating_record(to_micros(timing.raw()));
+        self.corrected
+            .saturating_record(to_micros(timing.corrected()));
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.raw.is_empty()
+    }
+
+    /// Raw percentiles in one line for the live progress output
+    pub fn live_summary(&self) -> String {
+        if self.is_empty() {
+            return String::new();
+        }
+        let values: Vec<String> = PERCENTILES
+            .iter()
+            .map(|(_, q)| format!("{:.0}", ms(self.raw.value_at_quantile(*q) as f64)))
+            .chain(std::iter::once(format!("{:.0}", ms(self.raw.max() as f64))))
+            .collect();
+        format!("p50/p90/p99/p99.9/max: {} ms", values.join("/"))
+    }
+
+    fn print_lines(&self, label: &str) {
+        print_histogram_line(&format!("{} raw", label), &self.raw);
+        print_histogram_line(&format!("{} corrected", label), &self.corrected);
+    }
+
+    pub fn to_json(&self) -> serde_json::Value {
+        serde_json::json!({
+            "raw": histogram_to_json(&self.raw),
+            "corrected": histogram_to_json(&self.corrected),
+        })
+    }
+}
+
+impl Default for LatencyStats {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Latency of a whole run, overall and per transaction type
+#[derive(Default)]
+pub struct LatencyReport {
+    all: LatencyStats,
+    by_kind: BTreeMap<TxKind, LatencyStats>,
+}
+
+impl LatencyReport {
+    pub fn record(&mut self, kind: TxKind, timing: &SendTiming) {
+        self.all.record(timing);
+        self.by_kind.entry(kind).or_default().record(timing);
+    }
+
+    pub fn all(&self) -> &LatencyStats {
+        &self.all
+    }
+
+    /// Prints the overall histograms, followed by one pair per type when more than one type ran
+    pub fn print_summary(&self, title: &str) {
+        if self.all.is_empty() {
+            return;
+        }
+        println!(
+            "{:<24} {:>10} {:>10} {:>10} {:>10} {:>10} {:>10}",
+            title, "mean", "p50", "p90", "p99", "p99.9", "max"
+        );
+        self.all.print_lines("all");
+        if self.by_kind.len() > 1 {
+            for (kind, stats) in &self.by_kind {
+                stats.print_lines(kind.as_str());
+            }
+        }
+    }
+
+    /// Full histograms of the run, written next to the transaction log
+    pub fn to_json(&self) -> serde_json::Value {
+        let by_kind: serde_json::Map<String, serde_json::Value> = self
+            .by_kind
+            .iter()
+            .map(|(kind, stats)| (kind.as_str().to_string(), stats.to_json()))
+            .collect();
+        serde_json::json!({
+            "all": self.all.to_json(),
+            "by_type": by_kind,
+        })
+    }
+}
+
+fn new_histogram() -> Histogram<u64> {
+    Histogram::new_with_bounds(1, MAX_LATENCY_MICROS, 3).expect("valid histogram bounds")
+}
+
+fn to_micros(duration: Duration) -> u64 {
+    duration.as_micros().clamp(1, MAX_LATENCY_MICROS as u128) as u64
+}
+
+fn ms(micros: f64) -> f64 {
+    micros / 1000.0
+}
+
+fn print_histogram_line(label: &str, histogram: &Histogram<u64>) {
+    print!("  {:<22} {:>10.2}", label, ms(histogram.mean()));
+    for (_, quantile) in PERCENTILES {
+        print!(
+            " {:>10.2}",
+            ms(histogram.value_at_quantile(quantile) as f64)
+        );
+    }
+    println!(" {:>10.2}", ms(histogram.max() as f64));
+}
+
+fn histogram_to_json(histogram: &Histogram<u64>) -> serde_json::Value {
+    let percentiles: serde_json::Map<String, serde_json::Value> = PERCENTILES
+        .iter()
+        .map(|(name, quantile)| {
+            let value = ms(histogram.value_at_quantile(*quantile) as f64);
+            (name.to_string(), serde_json::json!(value))
+        })
+        .collect();
+    let buckets: Vec<serde_json::Value> = histogram
+        .iter_recorded()
+        .map(|v| {
+            serde_json::json!({
+                "value_ms": ms(v.value_iterated_to() as f64),
+                "count": v.count_at_value(),
+                "percentile": v.percentile(),
+            })
+        })
+        .collect();
+
+    serde_json::json!({
+        "count": histogram.len(),
+        "min_ms": ms(histogram.min() as f64),
+        "mean_ms": ms(histogram.mean()),
+        "max_ms": ms(histogram.max() as f64),
+        "percentiles_ms": percentiles,
+        "buckets": buckets,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_corrected_latency_includes_schedule_lag() {
+        let intended = Instant::now();
+        let timing = SendTiming {
+            intended,
+            sent: intended + Duration::from_millis(400),
+            responded: intended + Duration::from_millis(500),
+        };
+
+        assert_eq!(timing.raw(), Duration::from_millis(100));
+        assert_eq!(timing.corrected(), Duration::from_millis(500));
+
+        let mut stats = LatencyStats::new();
+        stats.record(&timing);
+        assert_eq!(stats.raw.len(), 1);
+        assert!(stats.corrected.value_at_quantile(0.5) >= 499_000);
+        assert!(stats.raw.value_at_quantile(0.5) <= 100_100);
+    }
+
+    #[test]
+    fn test_report_keeps_per_type_histograms() {
+        let start = Instant::now();
+        let timing = |ms: u64| SendTiming {
+            intended: start,
+            sent: start,
+            responded: start + Duration::from_millis(ms),
+        };
+
+        let mut report = LatencyReport::default();
+        for ms in 1..=100 {
+            report.record(TxKind::Transfer, &timing(ms));
+        }
+        report.record(TxKind::Message, &timing(1000));
+
+        let json = report.to_json();
+        assert_eq!(json["all"]["raw"]["count"], 101);
+        assert_eq!(json["by_type"]["transfer"]["raw"]["count"], 100);
+        assert_eq!(json["by_type"]["message"]["corrected"]["count"], 1);
+
+        let p90 = json["by_type"]["transfer"]["raw"]["percentiles_ms"]["p90"]
+            .as_f64()
+            .unwrap();
+        assert!((89.0..=91.0).contains(&p90));
+        assert!(report
+            .all()
+            .live_summary()
+            .starts_with("p50/p90/p99/p99.9/max:"));
+    }
+}
@@ -1,54 +1,108 @@
 use crate::{
+    account_store::{self, NetworkKey, OnChainStatus},
     cli::verbose,
+    confirmation::{self, ConfirmationConfig},
     crypto::{self, ShardusCrypto},
-    proxy,
+    finality::{FinalityConfig, FinalityOutcome, FinalityStats, FinalityTracker},
+    funding::{self, FundingConfig},
+    hd_accounts::HdAccounts,
+    latency::{LatencyReport, SendTiming},
+    load_profile::{LoadProfile, ProfileTicker},
+    registration::{self, FailureCounts, RegistrationFailure},
     transactions::{self},
     utils,
+    workload::{Recipient, SenderSource, TransactionBuilder, TxKind, WorkloadMix},
 };
 use alloy::signers::local::PrivateKeySigner;  
 use rand::{self, Rng, SeedableRng};
 use rand::rngs::StdRng;
 use std::hash::Hasher;
-use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::sync::atomic::{AtomicUsize, Ordering};
 use std::sync::Arc;
 
-const ACCOUNTS_FILE: &str = "./artifacts/registered_accounts.json";
+/// How often the live progress line recomputes latency percentiles
+const LIVE_LATENCY_REFRESH: tokio::time::Duration = tokio::time::Duration::from_millis(500);
 
-#[derive(Serialize, Deserialize, Debug, Clone)]
-pub struct StoredAccount {
-    pub private_key: String,
-    pub address: String,
-    pub alias: String,
-    pub registration_tx_id: Option<String>,
-    pub registered_at: u128,
-}
-
-#[derive(Serialize, Deserialize, Debug)]
-pub struct AccountsStorage {
-    pub accounts: Vec<StoredAccount>,
-    pub last_updated: u128,
-}
+/// Completed requests waiting to be logged before senders have to wait
+const RESULT_CHANNEL_CAPACITY: usize = 4096;
 
 #[derive(Debug, Clone)]
 pub struct WalletWithTargets {
     pub wallet: PrivateKeySigner,
-    pub target_addresses: Vec<alloy::primitives::Address>,
+    pub targets: Vec<Recipient>,
 }
 
 #[derive(Debug)]
 pub struct LoadInjectParams {
     pub tx_type: String,
-    pub tps: usize,
+    pub profile: LoadProfile,
     pub duration: usize,
     pub eoa: usize,
     pub eoa_tps: usize,
     pub gateway_url: String,
     pub verbosity: bool,
     pub reuse_accounts: bool,
+    pub concurrency: Option<usize>,
+    pub mix: WorkloadMix,
+    /// `DEFAULT_MAX_IN_FLIGHT` when not set
+    pub max_in_flight: Option<usize>,
+    pub saturation_policy: SaturationPolicy,
+    /// Poll the gateway until every accepted transaction is applied, rejected or timed out
+    pub finality: Option<FinalityConfig>,
+    /// How `run` waits for freshly registered accounts before injecting
+    pub confirmation: ConfirmationConfig,
+    /// Reused accounts below this balance (in wei) are marked stale and replaced
+    pub min_balance: u128,
+    /// Derive the wallet pool from a mnemonic instead of random keys
+    pub mnemonic: Option<HdAccounts>,
+    /// Top the pool up from a funded key before injecting
+    pub funding: Option<FundingConfig>,
+}
+
+/// What the open-loop scheduler does when `max_in_flight` requests are already pending
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum SaturationPolicy {
+    /// Skip the send and count it as "generator saturated"
+    Drop,
+    /// Hold the schedule until a request completes and count the send as late
+    #[default]
+    Delay,
+}
+
+impl std::str::FromStr for SaturationPolicy {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "drop" => Ok(SaturationPolicy::Drop),
+            "delay" => Ok(SaturationPolicy::Delay),
+            _ => Err(format!(
+                "'{}' is not a valid saturation policy, expected drop or delay",
+                s
+            )),
+        }
+    }
+}
+
+/// Cap on pending gateway requests in open-loop mode unless `max_in_flight` is given
+pub const DEFAULT_MAX_IN_FLIGHT: usize = 1024;
+
+/// How long a closed-loop user waits after a send it could not make
+const SKIPPED_SEND_BACKOFF: tokio::time::Duration = tokio::time::Duration::from_millis(10);
+
+/// Sends the generator could not make on schedule because of the in-flight cap,
+/// or in closed loop because no sender with a target was found
+#[derive(Default)]
+struct SaturationCounters {
+    skipped: AtomicUsize,
+    late: AtomicUsize,
 }
 
 /// Deterministically pre-select target addresses for each wallet
 fn preselect_targets(wallets: &[PrivateKeySigner], targets_per_wallet: usize) -> Vec<WalletWithTargets> {
+    // a recipient's keys are derived once and shared by every sender that picks it
+    let recipients: Vec<Recipient> = wallets.iter().map(Recipient::of).collect();
     wallets
         .iter()
         .map(|sender_wallet| {
@@ -61,13 +115,13 @@ fn preselect_targets(wallets: &[PrivateKeySigner], targets_per_wallet: usize) ->
             };
             
             let mut rng = StdRng::seed_from_u64(seed);
-            let mut target_addresses = Vec::new();
+            let mut targets = Vec::new();
             
             // Get all possible target addresses (excluding sender)
-            let possible_targets: Vec<alloy::primitives::Address> = wallets
+            let possible_targets: Vec<Recipient> = recipients
                 .iter()
-                .map(|w| w.address())
-                .filter(|addr| *addr != sender_address)
+                .filter(|r| r.address != sender_address)
+                .cloned()
                 .collect();
             
             // If we don't have enough wallets, repeat the available ones
@@ -75,389 +129,540 @@ fn preselect_targets(wallets: &[PrivateKeySigner], targets_per_wallet: usize) ->
                 // Edge case: only one wallet, can't send to itself
                 return WalletWithTargets {
                     wallet: sender_wallet.clone(),
-                    target_addresses: vec![],
+                    targets: vec![],
                 };
             }
             
             // Select targets_per_wallet addresses (with repetition if necessary)
             for _ in 0..targets_per_wallet {
                 let idx = rng.gen_range(0..possible_targets.len());
-                target_addresses.push(possible_targets[idx]);
+                targets.push(possible_targets[idx].clone());
             }
             
             WalletWithTargets {
                 wallet: sender_wallet.clone(),
-                target_addresses,
+                targets,
             }
         })
         .collect()
 }
 
-pub async fn transfer(load_inject_params: LoadInjectParams) {
-    let LoadInjectParams {
-        tps,
-        duration,
-        eoa,
-        gateway_url,
-        verbosity,
-        eoa_tps,
-        reuse_accounts,
-        ..
-    } = load_inject_params;
-
-    let now = std::time::SystemTime::now()
-        .duration_since(std::time::UNIX_EPOCH)
-        .unwrap()
-        .as_millis();
-    let log_file_path = format!("./artifacts/test_transfer_{}.txt", now);
-    let shardus_crypto = Arc::new(crypto::ShardusCrypto::new(
-        "69fa4195670576c0160d660c3be36556ff8d504725be8a59b5a96509e0c994bc",
-    ));
-
-    let gateway_url_cloned = gateway_url.clone();
-
-    let mut wallets = get_wallets(
-        &eoa_tps,
-        &eoa,
-        &gateway_url_cloned,
-        Arc::clone(&shardus_crypto),
-        &verbosity,
-        reuse_accounts,
-    )
-    .await;
+/// Pick a random sender and one of its pre-selected targets
+fn pick_sender_and_target(
+    wallets: &[WalletWithTargets],
+) -> Option<(PrivateKeySigner, Recipient)> {
+    let from_idx = rand::thread_rng().gen_range(0..wallets.len());
+    let sender_wallet = &wallets[from_idx];
 
-    if reuse_accounts {
-        println!("Reusing existing accounts, skipping wait time and validation before injecting transactions");
-    } else {
-        println!("Waiting for 30 seconds before injecting transactions");
-        tokio::time::sleep(tokio::time::Duration::from_secs(30)).await;
-        wallets = validate_filter_failed_register(wallets, &gateway_url, &verbosity).await;
-    }
-
-    if wallets.len() < 2 {
-        println!("Couldn't register enough wallets to conduct test, shuting down...");
-        return;
+    // Skip if this wallet has no targets (edge case)
+    if sender_wallet.targets.is_empty() {
+        return None;
     }
 
-    println!("Registered {} successful wallets", wallets.len());
-
-    // Pre-select 20 target addresses for each wallet deterministically
-    let wallets_with_targets = preselect_targets(&wallets, 10);
-    println!("Pre-selected target addresses for each wallet");
+    let target_idx = rand::thread_rng().gen_range(0..sender_wallet.targets.len());
+    Some((
+        sender_wallet.wallet.clone(),
+        sender_wallet.targets[target_idx].clone(),
+    ))
+}
 
-    println!("Injecting transactions");
+/// Outcome of one injected transaction
+struct SendResult {
+    kind: TxKind,
+    tx: transactions::LiberdusTransactions,
+    resp: Result<transactions::InjectedTxResp, String>,
+    timing: SendTiming,
+}
 
-    let duration = tokio::time::Duration::from_secs(duration as u64);
-    let start_time = tokio::time::Instant::now();
-    let interval = tokio::time::Duration::from_secs_f64(1.0 / tps as f64);
-    let mut interval_timer = tokio::time::interval(interval);
+/// Build and inject one transaction with the given builder, `intended` is the
+/// time the schedule wanted it sent. Building signs and may seal a message or
+/// derive ML-KEM keys, so it runs on the blocking pool.
+async fn send_transaction(
+    builder: Arc<dyn TransactionBuilder>,
+    intended: tokio::time::Instant,
+    http_client: reqwest::Client,
+    shardus_crypto: Arc<ShardusCrypto>,
+    wallets: &[WalletWithTargets],
+    gateway_url: &String,
+    verbosity: &bool,
+) -> Option<SendResult> {
+    let (sender, target) = match builder.sender_source() {
+        SenderSource::Pool => {
+            let (sender, target) = pick_sender_and_target(wallets)?;
+            (sender, Some(target))
+        }
+        SenderSource::Fresh => (PrivateKeySigner::random(), None),
+    };
 
-    let (transmitter, mut receiver) = tokio::sync::mpsc::unbounded_channel::<(
-        transactions::TransferTransaction,
-        Result<transactions::InjectedTxResp, String>,
-    )>();
+    let kind = builder.kind();
+    let tx = tokio::task::spawn_blocking(move || {
+        builder.build(&shardus_crypto, &sender, target.as_ref())
+    })
+    .await
+    .expect("transaction builder panicked");
+    let sent = tokio::time::Instant::now();
+    let resp =
+        match transactions::inject_transaction(http_client, &tx, gateway_url, verbosity).await {
+            Ok(resp) => Ok(resp),
+            Err(e) => Err(e.to_string()),
+        };
+    let responded = tokio::time::Instant::now();
+
+    Some(SendResult {
+        kind,
+        tx,
+        resp,
+        timing: SendTiming {
+            intended,
+            sent,
+            responded,
+        },
+    })
+}
 
-    let gateway_url_long_live = gateway_url.clone();
-    tokio::spawn(async move {
-        // uses ARC internally
-        let http_client = reqwest::Client::builder()
-            .danger_accept_invalid_certs(true)
-            .build()
-            .unwrap();
+/// Drive a workload: prepare the wallet pool, inject transactions following the
+/// profile or closed-loop concurrency, then log and report every result.
+/// Stored accounts checked out for the run are released at the end, or on Ctrl-C.
+pub async fn run(load_inject_params: LoadInjectParams) {
+    let verbosity = load_inject_params.verbosity;
+    tokio::select! {
+        _ = run_workload(load_inject_params) => {}
+        _ = tokio::signal::ctrl_c() => {
+            println!("\nInterrupted");
+            release_account_leases(&verbosity);
+            std::process::exit(130);
+        }
+    }
+    release_account_leases(&verbosity);
+}
 
-        let long_live_transmitter = transmitter.clone();
+/// Hand the stored accounts this run checked out back to the store
+pub fn release_account_leases(verbosity: &bool) {
+    match account_store::release_leases() {
+        Ok(0) => {}
+        Ok(released) => verbose(
+            verbosity,
+            &format!("Released {} leased accounts", released),
+        ),
+        Err(e) => eprintln!(
+            "Failed to release leased accounts, they free up after the lease timeout: {}",
+            e
+        ),
+    }
+}
 
-        let sc = Arc::clone(&shardus_crypto);
-        let long_live_wallet = wallets_with_targets.clone();
-        while start_time.elapsed() < duration {
-            interval_timer.tick().await;
-            let sc = Arc::clone(&sc);
-            let wl = long_live_wallet.clone();
-            let http_client = http_client.clone();
+async fn run_workload(load_inject_params: LoadInjectParams) {
+    let shardus_crypto = Arc::new(crypto::ShardusCrypto::new(
+        "69fa4195670576c0160d660c3be36556ff8d504725be8a59b5a96509e0c994bc",
+    ));
 
-            // Select a random sender wallet
-            let from_idx = rand::thread_rng().gen_range(0..wl.len());
-            let sender_wallet = wl[from_idx].clone();
-            
-            // Skip if this wallet has no targets (edge case)
-            if sender_wallet.target_addresses.is_empty() {
-                continue;
+    let wallets = if needs_wallets(&load_inject_params.mix) {
+        let wallets = match &load_inject_params.mnemonic {
+            Some(hd_accounts) => {
+                get_hd_wallets(
+                    &load_inject_params.eoa_tps,
+                    &load_inject_params.eoa,
+                    &load_inject_params.gateway_url,
+                    Arc::clone(&shardus_crypto),
+                    &load_inject_params.verbosity,
+                    hd_accounts,
+                )
+                .await
             }
-            
-            // Select a random target from the sender's pre-selected targets
-            let target_idx = rand::thread_rng().gen_range(0..sender_wallet.target_addresses.len());
-            let to_address = sender_wallet.target_addresses[target_idx];
-
-            let transmitter = long_live_transmitter.clone();
-
-            let gateway_url_for_detached_thread = gateway_url_long_live.clone();
-            tokio::spawn(async move {
-                let signers = sender_wallet.wallet.clone();
-                let tx = transactions::build_transfer_transaction(
-                    &Arc::clone(&sc),
-                    &signers,
-                    &to_address,
-                    1,
-                );
-                let resp = match transactions::inject_transaction(
-                    http_client,
-                    &transactions::LiberdusTransactions::Transfer(tx.clone()),
-                    &gateway_url_for_detached_thread,
-                    &verbosity,
+            None => {
+                get_wallets(
+                    &load_inject_params.eoa_tps,
+                    &load_inject_params.eoa,
+                    &load_inject_params.gateway_url,
+                    Arc::clone(&shardus_crypto),
+                    &load_inject_params.verbosity,
+                    load_inject_params.reuse_accounts,
+                    load_inject_params.min_balance,
                 )
                 .await
-                {
-                    Ok(resp) => Ok(resp),
-                    Err(e) => Err(e.to_string()),
-                };
+            }
+        };
 
-                transmitter.send((tx, resp)).unwrap();
-            });
+        // validated reused accounts confirm on the first lookup, only fresh
+        // registrations and replacements of stale accounts are waited for
+        println!("\nWaiting for registrations to be confirmed before injecting transactions");
+        let accepted = wallets.len();
+        let wallets = confirmation::wait_for_registrations(
+            wallets,
+            &load_inject_params.gateway_url,
+            &load_inject_params.confirmation,
+            &load_inject_params.verbosity,
+        )
+        .await;
+        if let Some(shortfall) =
+            registration::shortfall(load_inject_params.eoa, accepted, wallets.len())
+        {
+            eprintln!("{}", shortfall);
         }
-    });
-
-    let mut stats = utils::InjectionStats {
-        total: 0,
-        success: 0,
-        failed: 0,
-    };
-
-    while let Some((tx, resp)) = receiver.recv().await {
-        let from = tx.from.clone();
-        let to = tx.to.clone();
-        stats.total += 1;
-
-        let dump = serde_json::json!({
-            "tx": tx,
-            "result": match resp {
-                Ok(resp) => {
-                    if resp.success {
-                        stats.success += 1;
-                    }
-                    else {
-                        stats.failed += 1;
-                    }
-                    resp
-                },
-                Err(e_str) => {
-                    verbose(&verbosity, &format!("Transfer failed from {}, to {}", from, to));
-                    stats.failed += 1;
 
-                    transactions::InjectedTxResp {
-                        success: false,
-                        reason: e_str,
-                        status: 500,
-                        txId: None
+        println!("Registered {} successful wallets", wallets.len());
+        match &load_inject_params.funding {
+            Some(funding_config) => {
+                match funding::fund_accounts(
+                    wallets,
+                    &load_inject_params.gateway_url,
+                    Arc::clone(&shardus_crypto),
+                    funding_config,
+                    &load_inject_params.verbosity,
+                )
+                .await
+                {
+                    Ok(funded) => funded,
+                    Err(e) => {
+                        eprintln!("Funding failed, no load was sent: {}", e);
+                        return;
                     }
                 }
             }
+            None => wallets,
+        }
+    } else {
+        Vec::new()
+    };
 
-        });
-
-        let _ = utils::append_json_to_file(&log_file_path, &dump);
-        utils::stdout_injection_stats(&stats, &verbosity);
-    }
+    inject(load_inject_params, &wallets, shardus_crypto).await;
+}
 
-    println!(
-        "\rTotal: {:<10} Success: {:<10} Failed: {:<10}",
-        stats.total, stats.success, stats.failed,
-    );
+/// Whether any transaction type of the workload sends from the wallet pool
+pub fn needs_wallets(mix: &WorkloadMix) -> bool {
+    mix.kinds()
+        .any(|kind| kind.builder().sender_source() == SenderSource::Pool)
 }
 
-pub async fn message(load_inject_params: LoadInjectParams) {
+/// Inject a workload from an already prepared wallet pool
+pub async fn inject(
+    load_inject_params: LoadInjectParams,
+    wallets: &[PrivateKeySigner],
+    shardus_crypto: Arc<ShardusCrypto>,
+) {
     let LoadInjectParams {
-        tps,
+        tx_type,
+        profile,
         duration,
-        eoa,
         gateway_url,
         verbosity,
-        eoa_tps,
-        reuse_accounts,
+        concurrency,
+        mix,
+        max_in_flight,
+        saturation_policy,
+        finality,
         ..
     } = load_inject_params;
-    let shardus_crypto = Arc::new(crypto::ShardusCrypto::new(
-        "69fa4195670576c0160d660c3be36556ff8d504725be8a59b5a96509e0c994bc",
-    ));
 
-    let gateway_url_cloned = gateway_url.clone();
+    let now = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .unwrap()
+        .as_millis();
+    let log_file_path = format!("./artifacts/test_{}_{}.txt", tx_type, now);
+    let latency_file_path = format!("./artifacts/latency_{}_{}.json", tx_type, now);
+    let finality_file_path = format!("./artifacts/finality_{}_{}.json", tx_type, now);
 
-    let wallets = {
-        let mut w = get_wallets(
-            &eoa_tps,
-            &eoa,
-            &gateway_url_cloned,
-            Arc::clone(&shardus_crypto),
-            &verbosity,
-            reuse_accounts,
-        )
-        .await;
+    let builders: BTreeMap<TxKind, Arc<dyn TransactionBuilder>> =
+        mix.kinds().map(|kind| (kind, kind.builder())).collect();
 
-        // wait only if we are not reusing accounts
-        if reuse_accounts {
-            println!("Reusing existing accounts, skipping wait time before injecting Message transactions");
-            println!("Skipping account validation for reused accounts");
-            w
-        } else {
-            println!("Waiting for 30 seconds before injecting Message transactions");
-            tokio::time::sleep(tokio::time::Duration::from_secs(30)).await;
-            w = validate_filter_failed_register(w, &gateway_url, &verbosity).await;
-            w
+    let wallets_with_targets = if needs_wallets(&mix) {
+        if wallets.len() < 2 {
+            println!("Couldn't register enough wallets to conduct test, shuting down...");
+            return;
         }
-    };
-
-    println!("Registered {} successful wallets", wallets.len());
 
-    // Pre-select 20 target addresses for each wallet deterministically  
-    let wallets_with_targets = preselect_targets(&wallets, 10);
-    println!("Pre-selected target addresses for each wallet");
-
-    if wallets.len() < 2 {
-        println!("Not Enough Wallets to conduct testing...., Killing Process");
-        return;
-    }
+        let wallets_with_targets = preselect_targets(wallets, 10);
+        println!("Pre-selected target addresses for each wallet");
+        wallets_with_targets
+    } else {
+        Vec::new()
+    };
 
     println!("Injecting transactions");
 
     let duration = tokio::time::Duration::from_secs(duration as u64);
     let start_time = tokio::time::Instant::now();
-    let interval = tokio::time::Duration::from_secs_f64(1.0 / tps as f64);
-    let mut interval_timer = tokio::time::interval(interval);
-
-    let (transmitter, mut receiver) = tokio::sync::mpsc::unbounded_channel::<(
-        transactions::MessageTransaction,
-        Result<transactions::InjectedTxResp, String>,
-    )>();
-
-    let rpc_url_long_live = gateway_url.clone();
-
-    tokio::spawn(async move {
-        let long_live_transmitter = transmitter.clone();
-
-        let sc = Arc::clone(&shardus_crypto);
-        let long_live_wallet = Arc::new(wallets_with_targets).clone();
-        let http_client = reqwest::Client::builder()
-            .danger_accept_invalid_certs(true)
-            .build()
-            .unwrap();
-        while start_time.elapsed() < duration {
-            interval_timer.tick().await;
 
-            let sc = Arc::clone(&sc);
-
-            let wl = long_live_wallet.clone();
+    let start_unix_ms = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .unwrap()
+        .as_millis();
 
-            // Select a random sender wallet
-            let from_idx = rand::thread_rng().gen_range(0..wl.len());
-            let sender_wallet = wl[from_idx].clone();
-            
-            // Skip if this wallet has no targets (edge case)
-            if sender_wallet.target_addresses.is_empty() {
-                continue;
+    let (transmitter, mut receiver) =
+        tokio::sync::mpsc::channel::<SendResult>(RESULT_CHANNEL_CAPACITY);
+    let saturation = Arc::new(SaturationCounters::default());
+
+    // uses ARC internally
+    let http_client = reqwest::Client::builder()
+        .danger_accept_invalid_certs(true)
+        .build()
+        .unwrap();
+    let mut finality = finality.map(|config| {
+        FinalityTracker::new(config, http_client.clone(), &gateway_url, verbosity)
+    });
+    let wallets_with_targets = Arc::new(wallets_with_targets);
+    let builders = Arc::new(builders);
+    let mix = Arc::new(mix);
+
+    match concurrency {
+        Some(workers) => {
+            // Closed loop: every virtual user waits for the gateway before sending again
+            for _ in 0..workers {
+                let sc = Arc::clone(&shardus_crypto);
+                let wl = Arc::clone(&wallets_with_targets);
+                let builders = Arc::clone(&builders);
+                let mix = Arc::clone(&mix);
+                let http_client = http_client.clone();
+                let gateway_url = gateway_url.clone();
+                let transmitter = transmitter.clone();
+                let saturation = Arc::clone(&saturation);
+                tokio::spawn(async move {
+                    while start_time.elapsed() < duration {
+                        let builder = &builders[&mix.pick(&mut rand::thread_rng())];
+                        // a closed-loop user has no schedule to fall behind
+                        let Some(result) = send_transaction(
+                            Arc::clone(builder),
+                            tokio::time::Instant::now(),
+                            http_client.clone(),
+                            Arc::clone(&sc),
+                            &wl,
+                            &gateway_url,
+                            &verbosity,
+                        )
+                        .await
+                        else {
+                            // nothing was sent, so nothing was awaited either
+                            saturation.skipped.fetch_add(1, Ordering::Relaxed);
+                            tokio::time::sleep(SKIPPED_SEND_BACKOFF).await;
+                            continue;
+                        };
+                        if transmitter.send(result).await.is_err() {
+                            break;
+                        }
+                    }
+                });
             }
-            
-            // Select a random target from the sender's pre-selected targets
-            let target_idx = rand::thread_rng().gen_range(0..sender_wallet.target_addresses.len());
-            let to_address = sender_wallet.target_addresses[target_idx];
-
-            let transmitter = long_live_transmitter.clone();
-            let rpc_url_for_detached_thread = rpc_url_long_live.clone();
-            let http = http_client.clone();
+        }
+        None => {
+            let mut ticker = ProfileTicker::new(profile, start_time, duration);
+            let gateway_url_long_live = gateway_url.clone();
+            let long_live_transmitter = transmitter.clone();
+            let mix = Arc::clone(&mix);
+            let saturation = Arc::clone(&saturation);
+            let in_flight = Arc::new(tokio::sync::Semaphore::new(
+                max_in_flight.unwrap_or(DEFAULT_MAX_IN_FLIGHT),
+            ));
             tokio::spawn(async move {
-                let from = &sender_wallet.wallet;
-                let message = utils::generate_random_string(30);
-                let tx = transactions::build_message_transaction(
-                    &Arc::clone(&sc),
-                    from,
-                        &to_address,
-                        &message,
-                    );
-                    // println!("built message tx: {:?}", tx);
-                    
-                    // // Verify the message transaction signature
-                    // let verification_result = verify_message_transaction_signature(&tx, &Arc::clone(&sc), from);
-                    // match verification_result {
-                    //     Ok(is_valid) => {
-                    //         if is_valid {
-                    //             println!("✓ Message transaction signature is valid");
-                    //         } else {
-                    //             println!("✗ Message transaction signature is INVALID");
-                    //         }
-                    //     }
-                    //     Err(e) => {
-                    //         println!("✗ Error verifying signature: {}", e);
-                    //     }
-                    // }
-
-                let resp = match transactions::inject_transaction(
-                    http,
-                    &transactions::LiberdusTransactions::Message(tx.clone()),
-                    &rpc_url_for_detached_thread,
-                    &verbosity,
-                )
-                .await
-                {
-                    Ok(resp) => Ok(resp),
-                    Err(e) => Err(e.to_string()),
-                };
+                while let Some(intended) = ticker.tick().await {
+                    // the results are no longer read, the run is shutting down
+                    if long_live_transmitter.is_closed() {
+                        break;
+                    }
+                    let permit = match Arc::clone(&in_flight).try_acquire_owned() {
+                        Ok(permit) => permit,
+                        Err(_) => match saturation_policy {
+                            SaturationPolicy::Drop => {
+                                saturation.skipped.fetch_add(1, Ordering::Relaxed);
+                                continue;
+                            }
+                            SaturationPolicy::Delay => {
+                                saturation.late.fetch_add(1, Ordering::Relaxed);
+                                Arc::clone(&in_flight)
+                                    .acquire_owned()
+                                    .await
+                                    .expect("in-flight semaphore is never closed")
+                            }
+                        },
+                    };
 
-                transmitter.send((tx, resp)).unwrap();
+                    let builder = Arc::clone(&builders[&mix.pick(&mut rand::thread_rng())]);
+                    let sc = Arc::clone(&shardus_crypto);
+                    let wl = Arc::clone(&wallets_with_targets);
+                    let http_client = http_client.clone();
+                    let transmitter = long_live_transmitter.clone();
+                    let gateway_url_for_detached_thread = gateway_url_long_live.clone();
+                    tokio::spawn(async move {
+                        if let Some(result) = send_transaction(
+                            builder,
+                            intended,
+                            http_client,
+                            sc,
+                            &wl,
+                            &gateway_url_for_detached_thread,
+                            &verbosity,
+                        )
+                        .await
+                        {
+                            drop(permit);
+                            // a closed channel means the run is over, the ticker loop stops next tick
+                            let _ = transmitter.send(result).await;
+                        }
+                    });
+                }
             });
         }
-    });
-
-    let mut stats = utils::InjectionStats {
-        total: 0,
-        success: 0,
-        failed: 0,
-    };
-
-    let now = std::time::SystemTime::now()
-        .duration_since(std::time::UNIX_EPOCH)
-        .unwrap()
-        .as_millis();
-
-    let log_file_path = format!("./artifacts/test_message_{}.txt", now);
-
-    while let Some((tx, resp)) = receiver.recv().await {
-        let from = tx.from.clone();
-        let to = tx.to.clone();
+    }
+    drop(transmitter);
+
+    let mut stats = utils::InjectionStats::default();
+    let mut stats_by_kind: BTreeMap<TxKind, utils::InjectionStats> = BTreeMap::new();
+    let mut latency = LatencyReport::default();
+    let mut live_latency = String::new();
+    let mut live_latency_refreshed = start_time;
+    let mut finality_stats = FinalityStats::default();
+
+    while let Some(SendResult {
+        kind,
+        tx,
+        resp,
+        timing,
+    }) = receiver.recv().await
+    {
+        latency.record(kind, &timing);
+        let kind_stats = stats_by_kind.entry(kind).or_default();
         stats.total += 1;
+        kind_stats.total += 1;
 
-        let dump = serde_json::json!({
-            "tx": serde_json::to_value(&tx).expect(""),
-            "result": match resp {
-                Ok(resp) => {
-                    if resp.success {
-                        stats.success += 1;
-                    }
-                    else {
-                        stats.failed += 1;
-                    };
-                    resp
-                },
-                Err(e_str) => {
-                    verbose(&verbosity, &format!("Message failed from {}, to {}", from, to));
+        let result = match resp {
+            Ok(resp) => {
+                if resp.success {
+                    stats.success += 1;
+                    kind_stats.success += 1;
+                } else {
                     stats.failed += 1;
-                    transactions::InjectedTxResp {
-                        success: false,
-                        reason: e_str,
-                        status: 500,
-                        txId: None
-                    }
+                    kind_stats.failed += 1;
                 }
+                resp
             }
+            Err(e_str) => {
+                let tx_json = tx.to_json();
+                verbose(
+                    &verbosity,
+                    &format!(
+                        "{} failed from {}, to {}: {}",
+                        kind, tx_json["from"], tx_json["to"], e_str
+                    ),
+                );
+                stats.failed += 1;
+                kind_stats.failed += 1;
+                transactions::InjectedTxResp {
+                    success: false,
+                    reason: e_str,
+                    status: 500,
+                    txId: None,
+                }
+            }
+        };
 
+        if let (Some((tracker, _)), true, Some(tx_id)) =
+            (&finality, result.success, &result.txId)
+        {
+            tracker.track(kind, tx_id.clone(), timing);
+            finality_stats.tracked += 1;
+        }
 
+        let dump = serde_json::json!({
+            "type": kind.as_str(),
+            "tx": tx.to_json(),
+            "result": result,
+            "timing": timing.to_json(start_time, start_unix_ms),
         });
 
         let _ = utils::append_json_to_file(&log_file_path, &dump);
-        utils::stdout_injection_stats(&stats, &verbosity);
+        if let Some((_, outcomes)) = &mut finality {
+            while let Ok(outcome) = outcomes.try_recv() {
+                record_finality(
+                    &mut finality_stats,
+                    &outcome,
+                    &log_file_path,
+                    start_time,
+                    start_unix_ms,
+                );
+            }
+        }
+        stats.skipped = saturation.skipped.load(Ordering::Relaxed);
+        stats.late = saturation.late.load(Ordering::Relaxed);
+        // percentiles walk the whole histogram, refresh them a few times a second only
+        if live_latency_refreshed.elapsed() >= LIVE_LATENCY_REFRESH || live_latency.is_empty() {
+            live_latency = latency.all().live_summary();
+            live_latency_refreshed = tokio::time::Instant::now();
+        }
+        utils::stdout_injection_stats(&stats, &live_latency, &verbosity);
     }
 
+    stats.skipped = saturation.skipped.load(Ordering::Relaxed);
+    stats.late = saturation.late.load(Ordering::Relaxed);
+
     println!(
         "\rTotal: {:<10} Success: {:<10} Failed: {:<10}",
         stats.total, stats.success, stats.failed,
     );
+    if concurrency.is_none() {
+        println!(
+            "Generator saturated: {} sends skipped, {} sends late (max in flight: {})",
+            stats.skipped,
+            stats.late,
+            max_in_flight.unwrap_or(DEFAULT_MAX_IN_FLIGHT),
+        );
+    } else if stats.skipped > 0 {
+        println!("Skipped {} sends without a sender and target", stats.skipped);
+    }
+    if mix.is_mixed() {
+        for kind in mix.kinds() {
+            let kind_stats = stats_by_kind.remove(&kind).unwrap_or_default();
+            println!(
+                "  {:<10} Total: {:<10} Success: {:<10} Failed: {:<10}",
+                kind.as_str(),
+                kind_stats.total,
+                kind_stats.success,
+                kind_stats.failed,
+            );
+        }
+    }
+    latency.print_summary("Latency (ms)");
+    if let Err(e) = utils::write_json_to_file(&latency_file_path, &latency.to_json()) {
+        eprintln!("Failed to save latency histograms: {}", e);
+    }
+
+    if let Some((tracker, mut outcomes)) = finality {
+        // the receiver closes once the last tracking task has finished
+        drop(tracker);
+        if finality_stats.pending() > 0 {
+            println!(
+                "Waiting for {} transactions to be applied",
+                finality_stats.pending()
+            );
+        }
+        while let Some(outcome) = outcomes.recv().await {
+            record_finality(
+                &mut finality_stats,
+                &outcome,
+                &log_file_path,
+                start_time,
+                start_unix_ms,
+            );
+        }
+        finality_stats.print_summary();
+        if let Err(e) =
+            utils::write_json_to_file(&finality_file_path, &finality_stats.to_json())
+        {
+            eprintln!("Failed to save finality report: {}", e);
+        }
+    }
+}
+
+fn record_finality(
+    stats: &mut FinalityStats,
+    outcome: &FinalityOutcome,
+    log_file_path: &str,
+    start_time: tokio::time::Instant,
+    start_unix_ms: u128,
+) {
+    stats.record(outcome);
+    let _ = utils::append_json_to_file(
+        log_file_path,
+        &outcome.to_json(start_time, start_unix_ms),
+    );
 }
 
 /// Get wallets either by loading from file or registering new ones
@@ -468,60 +673,97 @@ pub async fn get_wallets(
     shardus_crypto: Arc<ShardusCrypto>,
     verbosity: &bool,
     reuse_accounts: bool,
+    min_balance: u128,
 ) -> Vec<PrivateKeySigner> {
+    let network = NetworkKey::current(gateway_url);
+
     if reuse_accounts {
-        // Try to load existing accounts first
-        match load_accounts_from_file(*eoa, verbosity).await {
-            Ok(loaded_accounts) => {
-                if loaded_accounts.len() >= *eoa {
-                    verbose(verbosity, &format!("Using {} loaded accounts", loaded_accounts.len()));
-                    return loaded_accounts.into_iter().take(*eoa).collect();
-                } else {
+        // lease and validate in batches, so a stale account is replaced by the
+        // next stored one and accounts are only checked out as far as needed
+        let mut loaded_accounts = Vec::new();
+        let (mut checked, mut stale, mut unreachable) = (0, 0, 0);
+        while loaded_accounts.len() < *eoa {
+            let batch = match account_store::load_accounts_from_file(
+                &network,
+                *eoa - loaded_accounts.len(),
+                verbosity,
+            )
+            .await
+            {
+                Ok(batch) => batch,
+                Err(e) => {
                     verbose(
                         verbosity,
-                        &format!(
-                            "Only {} accounts loaded, need {}. Registering {} more accounts.",
-                            loaded_accounts.len(),
-                            *eoa,
-                            *eoa - loaded_accounts.len()
-                        ),
+                        &format!("Failed to load accounts: {}. Registering new accounts.", e),
                     );
-                    
-                    // Register additional accounts needed
-                    let additional_needed = *eoa - loaded_accounts.len();
-                    let mut new_accounts = generate_register_wallets_internal(
-                        tps,
-                        &additional_needed,
-                        gateway_url,
-                        Arc::clone(&shardus_crypto),
-                        verbosity,
-                    )
-                    .await;
-                    
-                    // Save the new accounts
-                    let accounts_to_save: Vec<(PrivateKeySigner, String, Option<String>)> = new_accounts
-                        .iter()
-                        .map(|signer| (signer.clone(), utils::generate_random_string(10), None))
-                        .collect();
-                    
-                    if let Err(e) = save_accounts_to_file(&accounts_to_save, verbosity).await {
-                        verbose(verbosity, &format!("Failed to save new accounts: {}", e));
-                    }
-                    
-                    // Combine loaded and new accounts
-                    let mut combined_accounts = loaded_accounts;
-                    combined_accounts.append(&mut new_accounts);
-                    return combined_accounts;
+                    break;
                 }
+            };
+            if batch.is_empty() {
+                break;
             }
-            Err(e) => {
-                verbose(
-                    verbosity,
-                    &format!("Failed to load accounts: {}. Registering new accounts.", e),
-                );
-                // Fall through to register new accounts
+            checked += batch.len();
+            let validation =
+                validate_reused_accounts(batch, &network, min_balance, verbosity).await;
+            stale += validation.stale;
+            unreachable += validation.unreachable;
+            let usable = validation.usable.len();
+            loaded_accounts.extend(validation.usable);
+
+            // likely a network reset, the rest of the store is no better
+            if usable == 0 {
+                break;
             }
         }
+        println!(
+            "Validated {} stored accounts: {} usable, {} stale, {} unreachable",
+            checked,
+            loaded_accounts.len(),
+            stale,
+            unreachable
+        );
+
+        if loaded_accounts.len() >= *eoa {
+            verbose(verbosity, &format!("Using {} loaded accounts", loaded_accounts.len()));
+            return loaded_accounts;
+        }
+        verbose(
+            verbosity,
+            &format!(
+                "Only {} accounts loaded, need {}. Registering {} more accounts.",
+                loaded_accounts.len(),
+                *eoa,
+                *eoa - loaded_accounts.len()
+            ),
+        );
+
+        // Register additional accounts needed
+        let additional_needed = *eoa - loaded_accounts.len();
+        let mut new_accounts = generate_register_wallets_internal(
+            tps,
+            &additional_needed,
+            gateway_url,
+            Arc::clone(&shardus_crypto),
+            verbosity,
+        )
+        .await;
+
+        // Save the new accounts
+        let accounts_to_save: Vec<(PrivateKeySigner, String, Option<String>)> = new_accounts
+            .iter()
+            .map(|signer| (signer.clone(), registration::alias(&signer.address()), None))
+            .collect();
+
+        if let Err(e) =
+            account_store::save_accounts_to_file(&accounts_to_save, &network, true, verbosity).await
+        {
+            eprintln!("Failed to save new accounts to {}: {}", account_store::ACCOUNTS_FILE, e);
+        }
+
+        // Combine loaded and new accounts
+        let mut combined_accounts = loaded_accounts;
+        combined_accounts.append(&mut new_accounts);
+        return combined_accounts;
     }
     
     // Register new accounts
@@ -530,11 +772,13 @@ pub async fn get_wallets(
     // Save the newly registered accounts
     let accounts_to_save: Vec<(PrivateKeySigner, String, Option<String>)> = new_signers
         .iter()
-        .map(|signer| (signer.clone(), utils::generate_random_string(10), None))
+        .map(|signer| (signer.clone(), registration::alias(&signer.address()), None))
         .collect();
     
-    if let Err(e) = save_accounts_to_file(&accounts_to_save, verbosity).await {
-        verbose(verbosity, &format!("Failed to save accounts: {}", e));
+    if let Err(e) =
+        account_store::save_accounts_to_file(&accounts_to_save, &network, true, verbosity).await
+    {
+        eprintln!("Failed to save accounts to {}: {}", account_store::ACCOUNTS_FILE, e);
     }
     
     new_signers
@@ -550,6 +794,56 @@ pub async fn generate_register_wallets(
     generate_register_wallets_internal(tps, eoa, gateway_url, shardus_crypto, verbosity).await
 }
 
+/// Derive the pool of `eoa` accounts from the mnemonic and register the ones
+/// the network does not know yet. Derived accounts are rebuilt from the phrase
+/// on every run, so they are not saved to the account store.
+pub async fn get_hd_wallets(
+    tps: &usize,
+    eoa: &usize,
+    gateway_url: &String,
+    shardus_crypto: Arc<ShardusCrypto>,
+    verbosity: &bool,
+    hd_accounts: &HdAccounts,
+) -> Vec<PrivateKeySigner> {
+    let derived = match hd_accounts.derive(*eoa) {
+        Ok(derived) => derived,
+        Err(e) => panic!("{}", e),
+    };
+
+    let mut on_chain = Vec::new();
+    let mut to_register = Vec::new();
+    for (wallet, status) in account_store::check_accounts(derived, gateway_url).await {
+        match status {
+            OnChainStatus::Found { .. } => on_chain.push(wallet),
+            OnChainStatus::Missing => to_register.push(wallet),
+            // registering again is harmless, losing the index is not
+            OnChainStatus::Unreachable(e) => {
+                verbose(
+                    verbosity,
+                    &format!("Couldn't check account {}: {}", wallet.address(), e),
+                );
+                to_register.push(wallet);
+            }
+        }
+    }
+
+    let indices = hd_accounts.indices(*eoa);
+    println!(
+        "Derived accounts {}..{} from the mnemonic: {} already on-chain, registering {}",
+        indices.start,
+        indices.end,
+        on_chain.len(),
+        to_register.len()
+    );
+
+    if !to_register.is_empty() {
+        let registered =
+            register_wallets(tps, to_register, gateway_url, shardus_crypto, verbosity, false).await;
+        on_chain.extend(registered);
+    }
+    on_chain
+}
+
 async fn generate_register_wallets_internal(
     tps: &usize,
     eoa: &usize,
@@ -557,25 +851,47 @@ async fn generate_register_wallets_internal(
     shardus_crypto: Arc<ShardusCrypto>,
     verbosity: &bool,
 ) -> Vec<PrivateKeySigner> {
+    let wallets = (0..*eoa).map(|_| PrivateKeySigner::random()).collect();
+    register_wallets(tps, wallets, gateway_url, shardus_crypto, verbosity, true).await
+}
+
+/// Send a register transaction for every wallet at `tps`, returns the ones the
+/// gateway accepted. Failed registrations are sent again after a backoff until
+/// the retry budget is spent. With `fresh_keys` a rejected account or one whose
+/// alias is taken is retried under a new random key and alias, derived accounts keep theirs.
+async fn register_wallets(
+    tps: &usize,
+    wallets: Vec<PrivateKeySigner>,
+    gateway_url: &String,
+    shardus_crypto: Arc<ShardusCrypto>,
+    verbosity: &bool,
+    fresh_keys: bool,
+) -> Vec<PrivateKeySigner> {
+    let total = wallets.len();
     let mut signers = Vec::new();
     let interval = tokio::time::Duration::from_secs_f64(1.0 / *tps as f64);
     let mut interval_timer = tokio::time::interval(interval);
 
+    // registrations to send, first attempts and retries alike, with their attempt number
+    let (queue, mut queued) = tokio::sync::mpsc::unbounded_channel::<(PrivateKeySigner, u32)>();
+    for wallet in wallets {
+        queue.send((wallet, 0)).unwrap();
+    }
+
     let (transmitter, mut receiver) = tokio::sync::mpsc::unbounded_channel::<(
         PrivateKeySigner,
+        u32,
         Result<transactions::InjectedTxResp, String>,
     )>();
 
     let gateway_url = gateway_url.clone();
-    let eoa_moved = *eoa;
     let verbosity = *verbosity;
-    tokio::spawn(async move {
-        let transmitter = transmitter.clone();
+    let sender = tokio::spawn(async move {
         let http_client = reqwest::Client::builder()
             .danger_accept_invalid_certs(true)
             .build()
             .unwrap();
-        for _ in 0..eoa_moved {
+        while let Some((signer, attempt)) = queued.recv().await {
             interval_timer.tick().await;
             let crypto = Arc::clone(&shardus_crypto);
             let url = gateway_url.clone();
@@ -583,12 +899,17 @@ async fn generate_register_wallets_internal(
             let transmitter = transmitter.clone();
             let http_client = http_client.clone();
             tokio::spawn(async move {
-                let signer = PrivateKeySigner::random();
-                let tx = transactions::build_register_transaction(
-                    &Arc::clone(&crypto),
-                    &signer,
-                    &utils::generate_random_string(10),
-                );
+                // signing and deriving the ML-KEM key stay off the runtime's threads
+                let account = signer.clone();
+                let tx = tokio::task::spawn_blocking(move || {
+                    transactions::build_register_transaction(
+                        &crypto,
+                        &account,
+                        &registration::alias(&account.address()),
+                    )
+                })
+                .await
+                .expect("register transaction builder panicked");
                 let resp = match transactions::inject_transaction(
                     http_client,
                     &transactions::LiberdusTransactions::Register(tx.clone()),
@@ -601,208 +922,147 @@ async fn generate_register_wallets_internal(
                     Err(e) => Err(e.to_string()),
                 };
 
-                transmitter.send((signer, resp)).unwrap();
+                let _ = transmitter.send((signer, attempt, resp));
             });
         }
     });
 
-    while let Some((signer, resp)) = receiver.recv().await {
-        match resp {
-            Ok(resp) => {
-                if resp.success {
-                    signers.push(signer);
-                    utils::stdout_register_progress(*eoa, signers.len());
-                }
-                if !resp.success {
-                    verbose(
-                        &verbosity,
-                        &format!("Failed to register wallet: {}", resp.reason),
-                    );
-                }
-            }
-            Err(e_str) => {
-                verbose(
-                    &verbosity,
-                    &format!(
-                        "Failed to register wallet: {} Transaction Object likely malformed",
-                        e_str
-                    ),
-                );
-            }
+    let mut retries_left = registration::retry_budget(total);
+    let mut retries = 0;
+    let mut failures = FailureCounts::default();
+    let mut pending = total;
+    while pending > 0 {
+        let Some((signer, attempt, resp)) = receiver.recv().await else {
+            break;
+        };
+        let Some(failure) = RegistrationFailure::classify(&resp) else {
+            signers.push(signer);
+            utils::stdout_register_progress(total, signers.len());
+            pending -= 1;
+            continue;
         };
-    }
-
-    signers
-}
-
-async fn validate_filter_failed_register(
-    wallets: Vec<PrivateKeySigner>,
-    gateway_url: &str,
-    verbosity: &bool,
-) -> Vec<PrivateKeySigner> {
-    verbose(
-        verbosity,
-        "Filtering wallets that failed to register"
-            .to_string()
-            .as_str(),
-    );
-
-    let mut filtered_wallets = vec![];
-
-    let (tx, mut rx) = tokio::sync::mpsc::unbounded_channel::<PrivateKeySigner>();
 
-    for wallet in wallets.iter() {
-        let wallet = wallet.clone();
-        let transmitter = tx.clone();
-        let gateway_url_long_live = gateway_url.to_string();
+        verbose(
+            &verbosity,
+            &format!(
+                "Failed to register wallet {} (attempt {}): {}",
+                signer.address(),
+                attempt + 1,
+                failure
+            ),
+        );
+        failures.record(&failure);
+        if retries_left == 0 {
+            pending -= 1;
+            continue;
+        }
+        retries_left -= 1;
+        retries += 1;
 
+        // the alias follows the key, a collision needs a new key as well
+        let signer = match failure {
+            RegistrationFailure::Rejected(_) | RegistrationFailure::AliasTaken(_) if fresh_keys => {
+                PrivateKeySigner::random()
+            }
+            _ => signer,
+        };
+        let queue = queue.clone();
         tokio::spawn(async move {
-            let addr = utils::to_shardus_address(&wallet.address().to_string());
-            let url = format!("{}/account/{}", &gateway_url_long_live, addr);
-            let resp = proxy::get_request(None, &url).await;
-
-            if let Ok(resp) = resp {
-                let json: proxy::GetAccountResp =
-                    serde_json::from_value(resp).expect("Failed to parse gateway response");
-
-                if json.account.is_some() {
-                    transmitter.send(wallet.clone()).unwrap();
-                }
-            };
-
-            drop(transmitter);
+            tokio::time::sleep(registration::backoff(attempt + 1)).await;
+            let _ = queue.send((signer, attempt + 1));
         });
     }
-
-    drop(tx);
-
-    while let Some(wallet) = rx.recv().await {
+    sender.abort();
+
+    if signers.len() < total {
+        println!();
+        eprintln!(
+            "Registered {} of {} wallets, {} short after {} retries",
+            signers.len(),
+            total,
+            total - signers.len(),
+            retries
+        );
+        failures.print_summary();
+    } else if failures.total() > 0 {
         verbose(
-            verbosity,
-            format!(
-                "Successful Register {}",
-                utils::to_shardus_address(&wallet.address().to_string())
-            )
-            .as_str(),
+            &verbosity,
+            &format!("Registered all {} wallets after {} retries", total, retries),
         );
-        filtered_wallets.push(wallet);
     }
 
-    filtered_wallets
+    signers
 }
 
-/// Save registered accounts to JSON file
-pub async fn save_accounts_to_file(
-    accounts: &[(PrivateKeySigner, String, Option<String>)], // (signer, alias, tx_id)
-    verbosity: &bool,
-) -> Result<(), Box<dyn std::error::Error>> {
-    let now = std::time::SystemTime::now()
-        .duration_since(std::time::UNIX_EPOCH)
-        .unwrap()
-        .as_millis();
-
-    let stored_accounts: Vec<StoredAccount> = accounts
-        .iter()
-        .map(|(signer, alias, tx_id)| StoredAccount {
-            private_key: hex::encode(signer.to_bytes()),
-            address: signer.address().to_string(),
-            alias: alias.clone(),
-            registration_tx_id: tx_id.clone(),
-            registered_at: now,
-        })
-        .collect();
-
-    // Load existing accounts if file exists
-    let mut existing_storage = load_accounts_from_file_internal().unwrap_or_else(|_| AccountsStorage {
-        accounts: Vec::new(),
-        last_updated: now,
-    });
-
-    // Add new accounts to existing ones (avoid duplicates by address)
-    for new_account in stored_accounts {
-        if !existing_storage.accounts.iter().any(|acc| acc.address == new_account.address) {
-            existing_storage.accounts.push(new_account);
-        }
-    }
-
-    existing_storage.last_updated = now;
-
-    // Ensure artifacts directory exists
-    std::fs::create_dir_all("./artifacts")?;
-
-    // Write to file
-    let json = serde_json::to_string_pretty(&existing_storage)?;
-    std::fs::write(ACCOUNTS_FILE, json)?;
-
-    verbose(
-        verbosity,
-        &format!("Saved {} accounts to {}", existing_storage.accounts.len(), ACCOUNTS_FILE),
-    );
-
-    Ok(())
+/// Outcome of validating a batch of stored accounts
+pub struct ReuseValidation {
+    pub usable: Vec<PrivateKeySigner>,
+    pub stale: usize,
+    pub unreachable: usize,
 }
 
-/// Load accounts from JSON file
-pub async fn load_accounts_from_file(
-    max_accounts: usize,
+/// Check that stored accounts still exist on the network with at least
+/// `min_balance`. Stale ones are marked in the store, usable legacy ones are
+/// claimed for `network`.
+pub async fn validate_reused_accounts(
+    wallets: Vec<PrivateKeySigner>,
+    network: &NetworkKey,
+    min_balance: u128,
     verbosity: &bool,
-) -> Result<Vec<PrivateKeySigner>, Box<dyn std::error::Error>> {
-    let storage = load_accounts_from_file_internal()?;
-    
-    let accounts_to_load = std::cmp::min(max_accounts, storage.accounts.len());
-    let mut signers = Vec::new();
-
-    for stored_account in storage.accounts.iter().take(accounts_to_load) {
-        let private_key_bytes = hex::decode(&stored_account.private_key)?;
-        if private_key_bytes.len() != 32 {
-            return Err("Invalid private key length".into());
+) -> ReuseValidation {
+    let mut usable = Vec::new();
+    let mut stale = Vec::new();
+    let mut unreachable = 0;
+    for (wallet, status) in account_store::check_accounts(wallets, &network.gateway_url).await {
+        match status {
+            OnChainStatus::Found { balance } if balance >= min_balance => usable.push(wallet),
+            OnChainStatus::Found { balance } => {
+                verbose(
+                    verbosity,
+                    &format!(
+                        "Stale account {}: balance {} is below {}",
+                        wallet.address(),
+                        balance,
+                        min_balance
+                    ),
+                );
+                stale.push(wallet.address().to_string());
+            }
+            OnChainStatus::Missing => {
+                verbose(
+                    verbosity,
+                    &format!("Stale account {}: not found on the network", wallet.address()),
+                );
+                stale.push(wallet.address().to_string());
+            }
+            OnChainStatus::Unreachable(e) => {
+                verbose(
+                    verbosity,
+                    &format!("Couldn't check account {}: {}", wallet.address(), e),
+                );
+                unreachable += 1;
+            }
         }
-        let mut key_array = [0u8; 32];
-        key_array.copy_from_slice(&private_key_bytes);
-        let signer = PrivateKeySigner::from_bytes(&key_array.into())?;
-        signers.push(signer);
     }
 
-    verbose(
-        verbosity,
-        &format!("Loaded {} accounts from {}", signers.len(), ACCOUNTS_FILE),
-    );
-
-    Ok(signers)
-}
-
-/// Internal function to load accounts storage from file
-fn load_accounts_from_file_internal() -> Result<AccountsStorage, Box<dyn std::error::Error>> {
-    let content = std::fs::read_to_string(ACCOUNTS_FILE)?;
-    let storage: AccountsStorage = serde_json::from_str(&content)?;
-    Ok(storage)
-}
-
-/// Verify the signature of a message transaction
-fn verify_message_transaction_signature(
-    tx: &transactions::MessageTransaction,
-    shardus_crypto: &Arc<ShardusCrypto>,
-    signer: &PrivateKeySigner,
-) -> Result<bool, Box<dyn std::error::Error + Send + Sync>> {
-    // Reconstruct the original transaction JSON that was signed (without the signature)
-    let original_tx = serde_json::json!({
-        "from": tx.from,
-        "amount": tx.amount,
-        "to": tx.to,
-        "type": tx.transaction_type,
-        "chatId": tx.chatId,
-        "message": tx.message,
-        "networkId": tx.networkId,
-        "timestamp": tx.timestamp,
-        "xmessage": tx.xmessage,
-        "fee": tx.fee,
-    });
+    if !stale.is_empty() {
+        if let Err(e) = account_store::mark_stale_accounts(&stale) {
+            verbose(verbosity, &format!("Failed to mark stale accounts: {}", e));
+        }
+    }
+    let usable_addresses: Vec<String> = usable.iter().map(|w| w.address().to_string()).collect();
+    match account_store::claim_legacy_accounts(&usable_addresses, network) {
+        Ok(0) => {}
+        Ok(claimed) => verbose(
+            verbosity,
+            &format!("Claimed {} legacy accounts for {}", claimed, network),
+        ),
+        Err(e) => verbose(verbosity, &format!("Failed to claim legacy accounts: {}", e)),
+    }
 
-    // Use the new eth_verify_signature function
-    transactions::eth_verify_signature(
-        shardus_crypto,
-        &original_tx,
-        &tx.sign,
-        &signer.address(),
-    )
+    ReuseValidation {
+        usable,
+        stale: stale.len(),
+        unreachable,
+    }
 }
@@ -0,0 +1,317 @@
+use std::str::FromStr;
+use tokio::time::{Duration, Instant};
+
+/// How long the scheduler skips ahead while a profile asks for 0 TPS
+const IDLE_STEP: Duration = Duration::from_millis(100);
+
+/// Shape of the injection rate over the duration of a test.
+///
+/// Parsed from a `--profile` spec, all times are in seconds:
+///
+/// * `constant:TPS`
+/// * `ramp:FROM:TO` - linear from FROM to TO TPS over the whole duration
+/// * `step:FROM:TO:STEPS` - STEPS equal stages going from FROM to TO TPS
+/// * `spike:BASE:PEAK:EVERY:LENGTH` - BASE TPS, jumping to PEAK for LENGTH seconds every EVERY seconds
+/// * `sine:MEAN:AMPLITUDE:PERIOD` - MEAN TPS oscillating by AMPLITUDE with the given PERIOD
+#[derive(Debug, Clone, PartialEq)]
+pub enum LoadProfile {
+    Constant {
+        tps: f64,
+    },
+    Ramp {
+        from: f64,
+        to: f64,
+    },
+    Step {
+        from: f64,
+        to: f64,
+        steps: usize,
+    },
+    Spike {
+        base: f64,
+        peak: f64,
+        every: f64,
+        length: f64,
+    },
+    Sine {
+        mean: f64,
+        amplitude: f64,
+        period: f64,
+    },
+}
+
+impl LoadProfile {
+    /// Target TPS at `elapsed` seconds into a test lasting `total` seconds
+    pub fn tps_at(&self, elapsed: f64, total: f64) -> f64 {
+        let progress = if total > 0.0 {
+            (elapsed / total).clamp(0.0, 1.0)
+        } else {
+            0.0
+        };
+
+        let tps = match self {
+            LoadProfile::Constant { tps } => *tps,
+            LoadProfile::Ramp { from, to } => from + (to - from) * progress,
+            LoadProfile::Step { from, to, steps } => {
+                if *steps <= 1 {
+                    *from
+                } else {
+                    let stage = ((progress * *steps as f64) as usize).min(steps - 1);
+                    from + (to - from) * stage as f64 / (steps - 1) as f64
+                }
+            }
+            LoadProfile::Spike {
+                base,
+                peak,
+                every,
+                length,
+            } => {
+                // first spike starts after one full period of base load
+                if elapsed >= *every && elapsed % every < *length {
+                    *peak
+                } else {
+                    *base
+                }
+            }
+            LoadProfile::Sine {
+                mean,
+                amplitude,
+                period,
+            } => mean + amplitude * (2.0 * std::f64::consts::PI * elapsed / period).sin(),
+        };
+
+        tps.max(0.0)
+    }
+
+    /// Approximate number of transactions the profile injects over `total` seconds
+    pub fn expected_transactions(&self, total: f64) -> f64 {
+        let step: f64 = 0.1;
+        let mut elapsed = 0.0;
+        let mut sum = 0.0;
+        while elapsed < total {
+            let width = step.min(total - elapsed);
+            sum += self.tps_at(elapsed, total) * width;
+            elapsed += step;
+        }
+        sum
+    }
+}
+
+impl FromStr for LoadProfile {
+    type Err = String;
+
+    fn from_str(spec: &str) -> Result<Self, Self::Err> {
+        let mut parts = spec.split(':');
+        let shape = parts.next().unwrap_or_default();
+        let values: Vec<&str> = parts.collect();
+
+        let numbers = |expected: usize| -> Result<Vec<f64>, String> {
+            if values.len() != expected {
+                return Err(format!(
+                    "'{}' profile expects {} values, got {}",
+                    shape,
+                    expected,
+                    values.len()
+                ));
+            }
+            values
+                .iter()
+                .map(|v| match v.parse::<f64>() {
+                    Ok(n) if n.is_finite() && n >= 0.0 => Ok(n),
+                    _ => Err(format!("'{}' is not a valid non-negative number", v)),
+                })
+                .collect()
+        };
+
+        let profile = match shape {
+            "constant" => {
+                let n = numbers(1)?;
+                LoadProfile::Constant { tps: n[0] }
+            }
+            "ramp" => {
+                let n = numbers(2)?;
+                LoadProfile::Ramp {
+                    from: n[0],
+                    to: n[1],
+                }
+            }
+            "step" => {
+                let n = numbers(3)?;
+                if n[2] < 1.0 || n[2].fract() != 0.0 {
+                    return Err("step count must be a whole number of at least 1".to_string());
+                }
+                LoadProfile::Step {
+                    from: n[0],
+                    to: n[1],
+                    steps: n[2] as usize,
+                }
+            }
+            "spike" => {
+                let n = numbers(4)?;
+                if n[2] <= 0.0 || n[3] > n[2] {
+                    return Err(
+                        "spike interval must be positive and not shorter than the spike length"
+                            .to_string(),
+                    );
+                }
+                LoadProfile::Spike {
+                    base: n[0],
+                    peak: n[1],
+                    every: n[2],
+                    length: n[3],
+                }
+            }
+            "sine" => {
+                let n = numbers(3)?;
+                if n[2] <= 0.0 {
+                    return Err("sine period must be positive".to_string());
+                }
+                LoadProfile::Sine {
+                    mean: n[0],
+                    amplitude: n[1],
+                    period: n[2],
+                }
+            }
+            _ => {
+                return Err(format!(
+                    "unknown profile '{}', expected one of constant, ramp, step, spike, sine",
+                    shape
+                ))
+            }
+        };
+
+        Ok(profile)
+    }
+}
+
+/// Paces transaction sends so that the rate follows a `LoadProfile`.
+///
+/// Send times are kept on an absolute schedule, a slow consumer gets the
+/// missed sends back to back instead of silently lowering the rate.
+pub struct ProfileTicker {
+    profile: LoadProfile,
+    start: Instant,
+    duration: Duration,
+    next: Instant,
+}
+
+impl ProfileTicker {
+    pub fn new(profile: LoadProfile, start: Instant, duration: Duration) -> Self {
+        ProfileTicker {
+            profile,
+            start,
+            duration,
+            next: start,
+        }
+    }
+
+    /// Waits for the next scheduled send, returns `None` once the duration is over
+    pub async fn tick(&mut self) -> Option<Instant> {
+        loop {
+            let elapsed = self.next.duration_since(self.start);
+            if elapsed >= self.duration {
+                return None;
+            }
+
+            let tps = self
+                .profile
+                .tps_at(elapsed.as_secs_f64(), self.duration.as_secs_f64());
+            if tps <= 0.0 {
+                self.next += IDLE_STEP;
+                continue;
+            }
+
+            let scheduled = self.next;
+            tokio::time::sleep_until(scheduled).await;
+            self.next = scheduled + Duration::from_secs_f64(1.0 / tps);
+            return Some(scheduled);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_profiles() {
+        assert_eq!(
+            "constant:5".parse::<LoadProfile>(),
+            Ok(LoadProfile::Constant { tps: 5.0 })
+        );
+        assert_eq!(
+            "ramp:1:50".parse::<LoadProfile>(),
+            Ok(LoadProfile::Ramp {
+                from: 1.0,
+                to: 50.0
+            })
+        );
+        assert_eq!(
+            "step:10:40:4".parse::<LoadProfile>(),
+            Ok(LoadProfile::Step {
+                from: 10.0,
+                to: 40.0,
+                steps: 4
+            })
+        );
+        assert!("spike:5:100:60:10".parse::<LoadProfile>().is_ok());
+        assert!("sine:20:10:60".parse::<LoadProfile>().is_ok());
+
+        assert!("ramp:1".parse::<LoadProfile>().is_err());
+        assert!("step:1:2:0".parse::<LoadProfile>().is_err());
+        assert!("spike:5:100:10:60".parse::<LoadProfile>().is_err());
+        assert!("constant:-1".parse::<LoadProfile>().is_err());
+        assert!("square:1:2".parse::<LoadProfile>().is_err());
+    }
+
+    #[test]
+    fn test_tps_at() {
+        let ramp = LoadProfile::Ramp {
+            from: 0.0,
+            to: 100.0,
+        };
+        assert_eq!(ramp.tps_at(0.0, 100.0), 0.0);
+        assert_eq!(ramp.tps_at(50.0, 100.0), 50.0);
+        assert_eq!(ramp.tps_at(150.0, 100.0), 100.0);
+
+        let step = LoadProfile::Step {
+            from: 10.0,
+            to: 40.0,
+            steps: 4,
+        };
+        assert_eq!(step.tps_at(0.0, 100.0), 10.0);
+        assert_eq!(step.tps_at(30.0, 100.0), 20.0);
+        assert_eq!(step.tps_at(60.0, 100.0), 30.0);
+        assert_eq!(step.tps_at(99.0, 100.0), 40.0);
+
+        let spike = LoadProfile::Spike {
+            base: 5.0,
+            peak: 100.0,
+            every: 60.0,
+            length: 10.0,
+        };
+        assert_eq!(spike.tps_at(5.0, 300.0), 5.0);
+        assert_eq!(spike.tps_at(65.0, 300.0), 100.0);
+        assert_eq!(spike.tps_at(75.0, 300.0), 5.0);
+
+        let sine = LoadProfile::Sine {
+            mean: 10.0,
+            amplitude: 20.0,
+            period: 40.0,
+        };
+        assert!((sine.tps_at(10.0, 100.0) - 30.0).abs() < 1e-9);
+        assert_eq!(sine.tps_at(30.0, 100.0), 0.0);
+    }
+
+    #[test]
+    fn test_expected_transactions() {
+        let constant = LoadProfile::Constant { tps: 4.0 };
+        assert!((constant.expected_transactions(60.0) - 240.0).abs() < 1e-6);
+
+        let ramp = LoadProfile::Ramp {
+            from: 0.0,
+            to: 10.0,
+        };
+        assert!((ramp.expected_transactions(100.0) - 500.0).abs() < 1.0);
+    }
+}
@@ -1,12 +1,26 @@
+mod account_store;
 mod change_config;
 mod cli;
+mod confirmation;
 mod crypto;
+mod economics;
+mod finality;
+mod funding;
+mod hd_accounts;
+mod keystore;
+mod latency;
 mod load_injector;
+mod load_profile;
 mod monitor_server;
 mod proxy;
+mod registration;
+mod scenario;
+mod stringify;
 mod stake;
 mod transactions;
 mod utils;
+mod workload;
+mod xmessage;
 
 #[tokio::main]
 async fn main() {
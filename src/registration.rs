@@ -0,0 +1,187 @@
+use crate::transactions::InjectedTxResp;
+use alloy::primitives::Address;
+use std::collections::BTreeMap;
+use std::sync::OnceLock;
+use tokio::time::Duration;
+
+/// Wait before the first retry of an account, doubled on every further attempt
+const FIRST_BACKOFF: Duration = Duration::from_millis(500);
+const MAX_BACKOFF: Duration = Duration::from_secs(10);
+
+/// Retries the whole run may spend on failed registrations, see `retry_budget`
+static REGISTER_RETRIES: OnceLock<usize> = OnceLock::new();
+
+pub fn set_register_retries(retries: usize) -> Result<(), String> {
+    REGISTER_RETRIES
+        .set(retries)
+        .map_err(|_| "register retries already set".to_string())
+}
+
+/// Retries for registering `total` accounts, one per account unless set otherwise
+pub fn retry_budget(total: usize) -> usize {
+    REGISTER_RETRIES.get().copied().unwrap_or(total)
+}
+
+/// Shortfall of a registration once it is confirmed: `wanted` accounts were asked for,
+/// the gateway accepted `accepted` and `confirmed` showed up on-chain. `None` when none is missing.
+pub fn shortfall(wanted: usize, accepted: usize, confirmed: usize) -> Option<String> {
+    if confirmed >= wanted {
+        return None;
+    }
+    Some(format!(
+        "Registered {} of {} wallets, {} short: {} not accepted by the gateway, {} unconfirmed",
+        confirmed,
+        wanted,
+        wanted - confirmed,
+        wanted.saturating_sub(accepted),
+        accepted.saturating_sub(confirmed)
+    ))
+}
+
+/// Alias an account registers under, so its username is known wherever its address is
+pub fn alias(address: &Address) -> String {
+    format!("load{}", hex::encode(&address[..6]))
+}
+
+/// Wait before attempt `attempt` (1 being the first retry) of an account
+pub fn backoff(attempt: u32) -> Duration {
+    FIRST_BACKOFF
+        .saturating_mul(2u32.saturating_pow(attempt.saturating_sub(1)))
+        .min(MAX_BACKOFF)
+}
+
+/// Why a register transaction did not go through
+#[derive(Debug, Clone, PartialEq)]
+pub enum RegistrationFailure {
+    /// The gateway could not be reached or gave no usable answer
+    Network(String),
+    /// Somebody registered the alias first, a fresh key and with it a fresh alias fixes it
+    AliasTaken(String),
+    /// The network refused the transaction for another reason
+    Rejected(String),
+}
+
+impl RegistrationFailure {
+    /// The failure behind an injection result, `None` when it was accepted
+    pub fn classify(resp: &Result<InjectedTxResp, String>) -> Option<RegistrationFailure> {
+        match resp {
+            Ok(resp) if resp.success => None,
+            Ok(resp) => {
+                let reason = resp.reason.to_lowercase();
+                let alias_taken = reason.contains("alias")
+                    && ["taken", "already", "exist", "in use"]
+                        .iter()
+                        .any(|word| reason.contains(word));
+                if alias_taken {
+                    Some(RegistrationFailure::AliasTaken(resp.reason.clone()))
+                } else {
+                    Some(RegistrationFailure::Rejected(resp.reason.clone()))
+                }
+            }
+            Err(e) => Some(RegistrationFailure::Network(e.clone())),
+        }
+    }
+}
+
+impl std::fmt::Display for RegistrationFailure {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            RegistrationFailure::Network(e) => write!(f, "network error: {}", e),
+            RegistrationFailure::AliasTaken(reason) => write!(f, "alias collision: {}", reason),
+            RegistrationFailure::Rejected(reason) => write!(f, "rejected: {}", reason),
+        }
+    }
+}
+
+/// Failed attempts of a registration run by kind, with the rejection reasons
+#[derive(Debug, Default)]
+pub struct FailureCounts {
+    pub network: usize,
+    pub alias_taken: usize,
+    pub rejected: usize,
+    reasons: BTreeMap<String, usize>,
+}
+
+impl FailureCounts {
+    pub fn record(&mut self, failure: &RegistrationFailure) {
+        match failure {
+            RegistrationFailure::Network(_) => self.network += 1,
+            RegistrationFailure::AliasTaken(_) => self.alias_taken += 1,
+            RegistrationFailure::Rejected(reason) => {
+                self.rejected += 1;
+                *self.reasons.entry(reason.clone()).or_default() += 1;
+            }
+        }
+    }
+
+    pub fn total(&self) -> usize {
+        self.network + self.alias_taken + self.rejected
+    }
+
+    pub fn print_summary(&self) {
+        println!(
+            "Failed attempts: {} network errors, {} alias collisions, {} rejected",
+            self.network, self.alias_taken, self.rejected
+        );
+        let mut reasons: Vec<_> = self.reasons.iter().collect();
+        reasons.sort_by(|a, b| b.1.cmp(a.1));
+        for (reason, count) in reasons.iter().take(5) {
+            println!("  {:>6}  {}", count, reason);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rejected(reason: &str) -> Result<InjectedTxResp, String> {
+        Ok(InjectedTxResp {
+            reason: reason.to_string(),
+            status: 400,
+            success: false,
+            txId: None,
+        })
+    }
+
+    #[test]
+    fn test_classify_failures() {
+        let accepted = Ok(InjectedTxResp {
+            reason: String::new(),
+            status: 200,
+            success: true,
+            txId: Some("ab".to_string()),
+        });
+        assert_eq!(RegistrationFailure::classify(&accepted), None);
+        assert!(matches!(
+            RegistrationFailure::classify(&rejected("This alias is already taken")),
+            Some(RegistrationFailure::AliasTaken(_))
+        ));
+        assert!(matches!(
+            RegistrationFailure::classify(&rejected("Invalid signature")),
+            Some(RegistrationFailure::Rejected(_))
+        ));
+        assert!(matches!(
+            RegistrationFailure::classify(&Err("connection refused".to_string())),
+            Some(RegistrationFailure::Network(_))
+        ));
+
+        assert_eq!(backoff(1), Duration::from_millis(500));
+        assert_eq!(backoff(3), Duration::from_secs(2));
+        assert_eq!(backoff(30), MAX_BACKOFF);
+    }
+
+    #[test]
+    fn test_shortfall() {
+        assert_eq!(shortfall(100, 100, 100), None);
+        assert_eq!(
+            shortfall(100, 96, 90).unwrap(),
+            "Registered 90 of 100 wallets, 10 short: 4 not accepted by the gateway, 6 unconfirmed"
+        );
+        // accounts reused from the store are accepted without a registration
+        assert_eq!(
+            shortfall(10, 10, 7).unwrap(),
+            "Registered 7 of 10 wallets, 3 short: 0 not accepted by the gateway, 3 unconfirmed"
+        );
+    }
+}
@@ -0,0 +1,712 @@
+use crate::{
+    account_store,
+    cli::verbose,
+    confirmation::{self, ConfirmationConfig},
+    crypto, economics,
+    finality::FinalityConfig,
+    funding::{self, FundingConfig},
+    hd_accounts::HdAccounts,
+    load_injector::{self, LoadInjectParams, SaturationPolicy},
+    load_profile::LoadProfile,
+    registration,
+    transactions,
+    workload::{TxKind, WorkloadMix},
+};
+use alloy::signers::local::PrivateKeySigner;
+use serde::Deserialize;
+use std::sync::Arc;
+use tokio::time::Duration;
+
+/// A whole test described in a TOML or JSON file
+///
+/// ```toml
+/// gateway_url = "https://dev.liberdus.com:3030"
+/// network_id = "liberdus-test"
+/// accounts = 500
+///
+/// [[phases]]
+/// kind = "register"
+/// tps = 10
+///
+/// [[phases]]
+/// kind = "wait_confirmed"
+/// timeout = 60
+/// min_confirmed = 95
+///
+/// [[phases]]
+/// kind = "load"
+/// tx_type = "message"
+/// tps = 50
+/// duration = 600
+/// ```
+#[derive(Deserialize, Debug)]
+#[serde(deny_unknown_fields)]
+pub struct Scenario {
+    pub gateway_url: String,
+    pub network_id: Option<String>,
+    /// Size of the account pool registered (or loaded) by `register` phases
+    pub accounts: usize,
+    #[serde(default)]
+    pub reuse_accounts: bool,
+    /// Reused accounts below this balance (in wei) are marked stale and replaced
+    pub min_balance: Option<u128>,
+    /// Derive the pool from this BIP-39 mnemonic, register phases take
+    /// consecutive indices starting at `mnemonic_start`
+    pub mnemonic: Option<String>,
+    pub mnemonic_start: Option<u32>,
+    /// Retries of failed registrations per register phase, one per account when unset
+    pub register_retries: Option<usize>,
+    /// Private key paying for `fund` phases, `FUNDING_KEY` when unset
+    pub funding_key: Option<String>,
+    #[serde(default)]
+    pub verbose: bool,
+    pub phases: Vec<Phase>,
+}
+
+#[derive(Deserialize, Debug)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum Phase {
+    /// Register accounts into the pool, `count` defaults to the scenario's `accounts`
+    Register {
+        count: Option<usize>,
+        #[serde(default = "default_register_tps")]
+        tps: usize,
+    },
+    /// Poll until registrations land, then drop accounts that never made it on-chain
+    WaitConfirmed {
+        /// Deadline in seconds
+        timeout: Option<u64>,
+        /// Milliseconds between account lookups
+        poll_interval: Option<u64>,
+        /// Percentage of accounts after which the wait may end early
+        min_confirmed: Option<f64>,
+    },
+    /// Top every pool account up to `target_balance` from the funding key
+    Fund {
+        target_balance: Wei,
+        fanout: Option<usize>,
+        transfer_fee: Option<Wei>,
+        /// Seconds to wait for one round of transfers to land
+        timeout: Option<u64>,
+    },
+    /// Inject a workload from the account pool
+    Load {
+        tx_type: Option<String>,
+        mix: Option<String>,
+        tps: Option<usize>,
+        profile: Option<String>,
+        concurrency: Option<usize>,
+        duration: usize,
+        max_in_flight: Option<usize>,
+        saturation_policy: Option<String>,
+        #[serde(default)]
+        track_finality: bool,
+        finality_timeout: Option<u64>,
+        finality_poll_interval: Option<u64>,
+    },
+    /// Idle between phases
+    Sleep { seconds: u64 },
+}
+
+fn default_register_tps() -> usize {
+    4
+}
+
+/// An amount in wei, TOML integers stop at `i64::MAX` so larger ones are written as strings
+#[derive(Deserialize, Debug, Clone)]
+#[serde(untagged)]
+pub enum Wei {
+    Number(u64),
+    Text(String),
+}
+
+impl Wei {
+    fn amount(&self) -> Result<u128, String> {
+        match self {
+            Wei::Number(amount) => Ok(*amount as u128),
+            Wei::Text(amount) => amount
+                .parse::<u128>()
+                .map_err(|_| format!("'{}' is not a valid amount of wei", amount)),
+        }
+    }
+}
+
+/// A load phase with its workload and pacing parsed
+struct LoadPhase {
+    tx_type: String,
+    mix: WorkloadMix,
+    profile: LoadProfile,
+    concurrency: Option<usize>,
+    duration: usize,
+    max_in_flight: Option<usize>,
+    saturation_policy: SaturationPolicy,
+    finality: Option<FinalityConfig>,
+}
+
+impl Scenario {
+    pub fn load(path: &str) -> Result<Scenario, Box<dyn std::error::Error>> {
+        let content = std::fs::read_to_string(path)?;
+        let scenario: Scenario = if path.ends_with(".json") {
+            serde_json::from_str(&content)?
+        } else {
+            toml::from_str(&content)?
+        };
+        scenario.validate()?;
+        Ok(scenario)
+    }
+
+    /// Check the whole scenario before anything is sent to the network
+    pub fn validate(&self) -> Result<(), String> {
+        if self.gateway_url.trim().is_empty() {
+            return Err("gateway_url must not be empty".to_string());
+        }
+        if self.phases.is_empty() {
+            return Err("scenario has no phases".to_string());
+        }
+        if self.mnemonic_start.is_some() && self.mnemonic.is_none() {
+            return Err("mnemonic_start needs a mnemonic".to_string());
+        }
+        if self.mnemonic.is_some() && self.reuse_accounts {
+            return Err("mnemonic and reuse_accounts cannot be combined".to_string());
+        }
+        if let Some(hd_accounts) = self.hd_accounts().transpose()? {
+            // register phases take consecutive ranges, all of them must fit the path
+            let registered = self
+                .phases
+                .iter()
+                .map(|phase| match phase {
+                    Phase::Register { count, .. } => count.unwrap_or(self.accounts),
+                    _ => 0,
+                })
+                .try_fold(0usize, |total, count| total.checked_add(count))
+                .ok_or_else(|| "register phases ask for too many accounts".to_string())?;
+            hd_accounts.check_range(registered)?;
+        }
+
+        // only register phases fill the pool, with reuse_accounts they check out stored accounts
+        let mut pool_available = false;
+        for (i, phase) in self.phases.iter().enumerate() {
+            let context = |e: String| format!("phase {} ({}): {}", i + 1, phase.name(), e);
+            match phase {
+                Phase::Register { count, tps } => {
+                    if count.unwrap_or(self.accounts) == 0 {
+                        return Err(context("nothing to register".to_string()));
+                    }
+                    if *tps == 0 {
+                        return Err(context("tps must be at least 1".to_string()));
+                    }
+                    pool_available = true;
+                }
+                Phase::WaitConfirmed { .. } => {
+                    if !pool_available {
+                        return Err(context("no accounts to wait for".to_string()));
+                    }
+                    phase.confirmation_config().map_err(context)?;
+                }
+                Phase::Fund { .. } => {
+                    if !pool_available {
+                        return Err(context("no accounts to fund".to_string()));
+                    }
+                    let funder = self.funder().map_err(context)?;
+                    phase.funding_config(funder).map_err(context)?;
+                }
+                Phase::Load { .. } => {
+                    let load = phase.load_phase().map_err(context)?;
+                    if load_injector::needs_wallets(&load.mix) && !pool_available {
+                        return Err(context(
+                            "workload needs an account pool, add a register phase before it".to_string(),
+                        ));
+                    }
+                }
+                Phase::Sleep { .. } => {}
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Time spent in load and sleep phases, registration and waits come on top
+    fn expected_duration(&self) -> Duration {
+        let seconds: u64 = self
+            .phases
+            .iter()
+            .map(|phase| match phase {
+                Phase::Load { duration, .. } => *duration as u64,
+                Phase::Sleep { seconds } => *seconds,
+                _ => 0,
+            })
+            .sum();
+        Duration::from_secs(seconds)
+    }
+
+    /// The funding key of the scenario, or `FUNDING_KEY`
+    fn funder(&self) -> Result<PrivateKeySigner, String> {
+        match &self.funding_key {
+            Some(key) => funding::parse_funding_key(key),
+            None => funding::funding_key_from_env().unwrap_or_else(|| {
+                Err(format!(
+                    "fund phases need a funding_key or {}",
+                    funding::FUNDING_KEY_ENV
+                ))
+            }),
+        }
+    }
+
+    fn hd_accounts(&self) -> Option<Result<HdAccounts, String>> {
+        self.mnemonic
+            .as_ref()
+            .map(|phrase| HdAccounts::new(phrase, self.mnemonic_start.unwrap_or(0)))
+    }
+}
+
+impl Phase {
+    fn name(&self) -> &'static str {
+        match self {
+            Phase::Register { .. } => "register",
+            Phase::WaitConfirmed { .. } => "wait_confirmed",
+            Phase::Fund { .. } => "fund",
+            Phase::Load { .. } => "load",
+            Phase::Sleep { .. } => "sleep",
+        }
+    }
+
+    fn confirmation_config(&self) -> Result<ConfirmationConfig, String> {
+        let Phase::WaitConfirmed {
+            timeout,
+            poll_interval,
+            min_confirmed,
+        } = self
+        else {
+            return Err("not a wait_confirmed phase".to_string());
+        };
+
+        let mut config = ConfirmationConfig::default();
+        if let Some(timeout) = timeout {
+            config.timeout = Duration::from_secs(*timeout);
+        }
+        match poll_interval {
+            Some(0) => return Err("poll_interval must be at least 1".to_string()),
+            Some(interval) => config.poll_interval = Duration::from_millis(*interval),
+            None => {}
+        }
+        if let Some(min_confirmed) = min_confirmed {
+            if !(*min_confirmed > 0.0 && *min_confirmed <= 100.0) {
+                return Err("min_confirmed must be a percentage between 0 and 100".to_string());
+            }
+            config.min_confirmed = *min_confirmed;
+        }
+        Ok(config)
+    }
+
+    fn funding_config(&self, funder: PrivateKeySigner) -> Result<FundingConfig, String> {
+        let Phase::Fund {
+            target_balance,
+            fanout,
+            transfer_fee,
+            timeout,
+        } = self
+        else {
+            return Err("not a fund phase".to_string());
+        };
+
+        let mut config = FundingConfig::new(funder, target_balance.amount()?);
+        if config.target_balance == 0 {
+            return Err("target_balance must be positive".to_string());
+        }
+        match fanout {
+            Some(0) => return Err("fanout must be at least 1".to_string()),
+            Some(fanout) => config.fanout = *fanout,
+            None => {}
+        }
+        if let Some(fee) = transfer_fee {
+            config.transfer_fee = fee.amount()?;
+        }
+        match timeout {
+            Some(0) => return Err("timeout must be at least 1 second".to_string()),
+            Some(timeout) => config.round_timeout = Duration::from_secs(*timeout),
+            None => {}
+        }
+        Ok(config)
+    }
+
+    fn load_phase(&self) -> Result<LoadPhase, String> {
+        let Phase::Load {
+            tx_type,
+            mix,
+            tps,
+            profile,
+            concurrency,
+            duration,
+            max_in_flight,
+            saturation_policy,
+            track_finality,
+            finality_timeout,
+            finality_poll_interval,
+        } = self
+        else {
+            return Err("not a load phase".to_string());
+        };
+
+        let (tx_type, mix) = match (tx_type, mix) {
+            (Some(tx_type), None) => (
+                tx_type.clone(),
+                WorkloadMix::single(tx_type.parse::<TxKind>()?),
+            ),
+            (None, Some(mix)) => ("mixed".to_string(), mix.parse::<WorkloadMix>()?),
+            _ => return Err("exactly one of tx_type or mix is required".to_string()),
+        };
+
+        let profile = match (tps, profile, concurrency) {
+            (Some(tps), None, None) if *tps > 0 => LoadProfile::Constant { tps: *tps as f64 },
+            (None, Some(profile), None) => profile.parse::<LoadProfile>()?,
+            (None, None, Some(workers)) if *workers > 0 => LoadProfile::Constant { tps: 0.0 },
+            _ => {
+                return Err(
+                    "exactly one of a positive tps, profile or concurrency is required".to_string(),
+                )
+            }
+        };
+
+        if *duration == 0 {
+            return Err("duration must be at least 1 second".to_string());
+        }
+
+        if *max_in_flight == Some(0) {
+            return Err("max_in_flight must be at least 1".to_string());
+        }
+        let saturation_policy = match saturation_policy {
+            Some(policy) => policy.parse::<SaturationPolicy>()?,
+            None => SaturationPolicy::default(),
+        };
+
+        let finality = match (track_finality, finality_timeout, finality_poll_interval) {
+            (false, None, None) => None,
+            (false, _, _) => {
+                return Err(
+                    "finality_timeout and finality_poll_interval need track_finality".to_string(),
+                )
+            }
+            (true, Some(0), _) | (true, _, Some(0)) => {
+                return Err("finality timeout and poll interval must be positive".to_string())
+            }
+            (true, timeout, interval) => {
+                let mut config = FinalityConfig::default();
+                if let Some(timeout) = timeout {
+                    config.timeout = Duration::from_secs(*timeout);
+                }
+                if let Some(interval) = interval {
+                    config.poll_interval = Duration::from_millis(*interval);
+                }
+                Some(config)
+            }
+        };
+
+        Ok(LoadPhase {
+            tx_type,
+            mix,
+            profile,
+            concurrency: *concurrency,
+            duration: *duration,
+            max_in_flight: *max_in_flight,
+            saturation_policy,
+            finality,
+        })
+    }
+}
+
+/// Run every phase of the scenario in order on one shared account pool
+pub async fn run(scenario: Scenario) {
+    if let Some(network_id) = &scenario.network_id {
+        if let Err(e) = transactions::set_network_id(network_id) {
+            panic!("Failed to set network id: {}", e);
+        }
+    }
+
+    account_store::set_lease_timeout(
+        account_store::DEFAULT_LEASE_TIMEOUT + scenario.expected_duration(),
+    )
+    .expect("lease timeout is set once");
+    if let Some(retries) = scenario.register_retries {
+        registration::set_register_retries(retries).expect("register retries are set once");
+    }
+    economics::load(&scenario.gateway_url).await;
+
+    tokio::select! {
+        _ = run_phases(&scenario) => {}
+        _ = tokio::signal::ctrl_c() => {
+            println!("\nInterrupted");
+            load_injector::release_account_leases(&scenario.verbose);
+            std::process::exit(130);
+        }
+    }
+    load_injector::release_account_leases(&scenario.verbose);
+
+    println!("\nScenario finished");
+}
+
+async fn run_phases(scenario: &Scenario) {
+    let shardus_crypto = Arc::new(crypto::ShardusCrypto::new(
+        "69fa4195670576c0160d660c3be36556ff8d504725be8a59b5a96509e0c994bc",
+    ));
+
+    let mut wallets: Vec<PrivateKeySigner> = Vec::new();
+    // accounts the register phases asked for, the pool a wait_confirmed phase should end with
+    let mut wanted = 0;
+    let mut hd_accounts = scenario
+        .hd_accounts()
+        .map(|hd_accounts| hd_accounts.expect("mnemonic was validated before the run"));
+    let total_phases = scenario.phases.len();
+
+    for (i, phase) in scenario.phases.iter().enumerate() {
+        println!("\nPhase {}/{}: {}", i + 1, total_phases, phase.name());
+
+        match phase {
+            Phase::Register { count, tps } => {
+                let count = count.unwrap_or(scenario.accounts);
+                wanted += count;
+                let registered = match hd_accounts.take() {
+                    Some(derive_from) => {
+                        // the next register phase continues after this one's indices
+                        hd_accounts = Some(derive_from.skip(count));
+                        load_injector::get_hd_wallets(
+                            tps,
+                            &count,
+                            &scenario.gateway_url,
+                            Arc::clone(&shardus_crypto),
+                            &scenario.verbose,
+                            &derive_from,
+                        )
+                        .await
+                    }
+                    None => {
+                        load_injector::get_wallets(
+                            tps,
+                            &count,
+                            &scenario.gateway_url,
+                            Arc::clone(&shardus_crypto),
+                            &scenario.verbose,
+                            scenario.reuse_accounts,
+                            scenario
+                                .min_balance
+                                .unwrap_or_else(|| economics::current().message_cost()),
+                        )
+                        .await
+                    }
+                };
+                let before = wallets.len();
+                for wallet in registered {
+                    // reused accounts may already be in the pool from an earlier phase
+                    if !wallets.iter().any(|w| w.address() == wallet.address()) {
+                        wallets.push(wallet);
+                    }
+                }
+                println!("\nAdded {} accounts to the pool", wallets.len() - before);
+            }
+            Phase::WaitConfirmed { .. } => {
+                let config = phase
+                    .confirmation_config()
+                    .expect("wait_confirmed phase was validated before the run");
+                let accepted = wallets.len();
+                wallets = confirmation::wait_for_registrations(
+                    wallets,
+                    &scenario.gateway_url,
+                    &config,
+                    &scenario.verbose,
+                )
+                .await;
+                if let Some(shortfall) = registration::shortfall(wanted, accepted, wallets.len()) {
+                    eprintln!("{}", shortfall);
+                }
+            }
+            Phase::Fund { .. } => {
+                let config = scenario
+                    .funder()
+                    .and_then(|funder| phase.funding_config(funder))
+                    .expect("fund phase was validated before the run");
+                wallets = match funding::fund_accounts(
+                    wallets,
+                    &scenario.gateway_url,
+                    Arc::clone(&shardus_crypto),
+                    &config,
+                    &scenario.verbose,
+                )
+                .await
+                {
+                    Ok(funded) => funded,
+                    Err(e) => {
+                        eprintln!("Funding failed, the remaining phases are skipped: {}", e);
+                        return;
+                    }
+                };
+            }
+            Phase::Load { .. } => {
+                let load = phase
+                    .load_phase()
+                    .expect("load phase was validated before the run");
+                let params = LoadInjectParams {
+                    tx_type: load.tx_type,
+                    profile: load.profile,
+                    duration: load.duration,
+                    eoa: wallets.len(),
+                    eoa_tps: 0,
+                    gateway_url: scenario.gateway_url.clone(),
+                    verbosity: scenario.verbose,
+                    reuse_accounts: scenario.reuse_accounts,
+                    concurrency: load.concurrency,
+                    mix: load.mix,
+                    max_in_flight: load.max_in_flight,
+                    saturation_policy: load.saturation_policy,
+                    finality: load.finality,
+                    confirmation: ConfirmationConfig::default(),
+                    min_balance: scenario
+                        .min_balance
+                        .unwrap_or_else(|| economics::current().message_cost()),
+                    mnemonic: None,
+                    funding: None,
+                };
+                verbose(&scenario.verbose, &format!("{:?}", params));
+                load_injector::inject(params, &wallets, Arc::clone(&shardus_crypto)).await;
+            }
+            Phase::Sleep { seconds } => {
+                println!("Sleeping for {} seconds", seconds);
+                tokio::time::sleep(Duration::from_secs(*seconds)).await;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_and_validate_toml() {
+        let scenario: Scenario = toml::from_str(
+            r#"
+            gateway_url = "http://0.0.0.0:3030"
+            accounts = 500
+            funding_key = "0xac0974bec39a17e36ba4a6b4d238ff944bacb478cbed5efcae784d7bf4f2ff80"
+
+            [[phases]]
+            kind = "register"
+            tps = 10
+
+            [[phases]]
+            kind = "wait_confirmed"
+            poll_interval = 500
+
+            [[phases]]
+            kind = "fund"
+            target_balance = "100000000000000000000"
+            fanout = 4
+
+            [[phases]]
+            kind = "load"
+            tx_type = "message"
+            tps = 50
+            duration = 600
+
+            [[phases]]
+            kind = "load"
+            mix = "transfer=90,message=10"
+            profile = "constant:200"
+            duration = 30
+            track_finality = true
+            finality_timeout = 120
+            "#,
+        )
+        .unwrap();
+
+        assert_eq!(scenario.phases.len(), 5);
+        assert!(scenario.validate().is_ok());
+        let funder = scenario.funder().unwrap();
+        let funding = scenario.phases[2].funding_config(funder).unwrap();
+        assert_eq!(funding.target_balance, 100_000_000_000_000_000_000);
+    }
+
+    #[test]
+    fn test_validate_rejects_bad_phases() {
+        let parse = |phases: &str| -> Scenario {
+            serde_json::from_str(&format!(
+                r#"{{"gateway_url": "http://0.0.0.0:3030", "accounts": 10, "phases": {}}}"#,
+                phases
+            ))
+            .unwrap()
+        };
+
+        // load before any account pool exists
+        let scenario =
+            parse(r#"[{"kind": "load", "tx_type": "transfer", "tps": 1, "duration": 5}]"#);
+        assert!(scenario.validate().is_err());
+
+        // reused accounts are checked out by a register phase, not by the flag alone
+        let reuse_only: Scenario = serde_json::from_str(
+            r#"{"gateway_url": "http://0.0.0.0:3030", "accounts": 10, "reuse_accounts": true,
+                "phases": [{"kind": "load", "tx_type": "transfer", "tps": 1, "duration": 5}]}"#,
+        )
+        .unwrap();
+        assert!(reuse_only.validate().is_err());
+        let reuse_registered: Scenario = serde_json::from_str(
+            r#"{"gateway_url": "http://0.0.0.0:3030", "accounts": 10, "reuse_accounts": true,
+                "phases": [{"kind": "register"}, {"kind": "load", "tx_type": "transfer", "tps": 1, "duration": 5}]}"#,
+        )
+        .unwrap();
+        assert!(reuse_registered.validate().is_ok());
+
+        // register needs no pool
+        let scenario =
+            parse(r#"[{"kind": "load", "tx_type": "register", "tps": 1, "duration": 5}]"#);
+        assert!(scenario.validate().is_ok());
+
+        // both tps and profile
+        let scenario = parse(
+            r#"[{"kind": "register"}, {"kind": "load", "tx_type": "message", "tps": 1, "profile": "ramp:1:2", "duration": 5}]"#,
+        );
+        assert!(scenario.validate().is_err());
+
+        // unknown tx type
+        let scenario = parse(
+            r#"[{"kind": "register"}, {"kind": "load", "tx_type": "stake", "tps": 1, "duration": 5}]"#,
+        );
+        assert!(scenario.validate().is_err());
+
+        // finality options without tracking
+        let scenario = parse(
+            r#"[{"kind": "register"}, {"kind": "load", "tx_type": "message", "tps": 1, "duration": 5, "finality_timeout": 10}]"#,
+        );
+        assert!(scenario.validate().is_err());
+
+        // confirmation threshold out of range
+        let scenario =
+            parse(r#"[{"kind": "register"}, {"kind": "wait_confirmed", "min_confirmed": 120}]"#);
+        assert!(scenario.validate().is_err());
+
+        // empty scenario
+        assert!(parse("[]").validate().is_err());
+
+        // invalid mnemonic, and a mnemonic next to reused accounts
+        let with_mnemonic = |extra: &str| -> Scenario {
+            serde_json::from_str(&format!(
+                r#"{{"gateway_url": "http://0.0.0.0:3030", "accounts": 10, {}, "phases": [{{"kind": "register"}}]}}"#,
+                extra
+            ))
+            .unwrap()
+        };
+        let phrase = "test test test test test test test test test test test junk";
+        assert!(with_mnemonic(&format!(r#""mnemonic": "{}", "mnemonic_start": 5"#, phrase))
+            .validate()
+            .is_ok());
+        assert!(with_mnemonic(r#""mnemonic": "test junk""#).validate().is_err());
+        assert!(with_mnemonic(&format!(r#""mnemonic": "{}", "reuse_accounts": true"#, phrase))
+            .validate()
+            .is_err());
+        assert!(with_mnemonic(r#""mnemonic_start": 5"#).validate().is_err());
+        // 10 accounts from 2^31 - 5 run past the last derivation index
+        assert!(with_mnemonic(&format!(
+            r#""mnemonic": "{}", "mnemonic_start": 2147483643"#,
+            phrase
+        ))
+        .validate()
+        .is_err());
+    }
+}
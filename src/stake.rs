@@ -1,7 +1,9 @@
 use crate::{
-    cli, crypto,
+    cli,
+    confirmation::{self, ConfirmationConfig},
+    crypto,
     load_injector::{self},
-    transactions,
+    registration, transactions,
 };
 use alloy::signers::{k256::ecdsa::SigningKey, local::LocalSigner};
 use reqwest::Client;
@@ -12,6 +14,7 @@ pub struct StakingParams {
     pub gateway_url: String,
     pub verbose: bool,
     pub stake_amount: u128,
+    pub confirmation: ConfirmationConfig,
 }
 
 #[allow(non_snake_case)]
@@ -26,7 +29,7 @@ pub struct Nominee {
 
 pub async fn stake_node(
     params: &StakingParams,
-    nominee: &String,
+    nominee: &str,
     nominator: &LocalSigner<SigningKey>,
     crypto: &crypto::ShardusCrypto,
 ) -> Result<transactions::InjectedTxResp, Box<dyn std::error::Error>> {
@@ -64,32 +67,37 @@ pub async fn stake(
 
     let target_wallet_count = nominees.len();
 
-    let mut wallet = Vec::new();
-
     cli::verbose(&params.verbose, "Generating wallets for staking...");
 
-    loop {
-        let w = load_injector::generate_register_wallets(
-            &1,
-            &target_wallet_count,
-            &params.gateway_url,
-            crypto.clone(),
-            &params.verbose,
-        )
-        .await;
+    // failed registrations are retried one by one, a shortfall is reported there
+    let wallet = load_injector::generate_register_wallets(
+        &1,
+        &target_wallet_count,
+        &params.gateway_url,
+        crypto.clone(),
+        &params.verbose,
+    )
+    .await;
 
-        wallet.extend(w);
-        if wallet.len() >= target_wallet_count {
-            break;
-        }
+    println!("Waiting for register transactions to propagate...");
+    let accepted = wallet.len();
+    let mut wallet = confirmation::wait_for_registrations(
+        wallet,
+        &params.gateway_url,
+        &params.confirmation,
+        &params.verbose,
+    )
+    .await;
+    if let Some(shortfall) = registration::shortfall(target_wallet_count, accepted, wallet.len()) {
+        eprintln!("{}", shortfall);
     }
 
-    println!("Sleeping for 30 seconds to let register transactions propagate...");
-    tokio::time::sleep(tokio::time::Duration::from_secs(30)).await;
-
     let mut green_staker = Vec::new();
     for nominee in nominees {
-        let nominator = wallet.pop().unwrap();
+        let Some(nominator) = wallet.pop() else {
+            eprintln!("No confirmed nominator left to stake node: {}", nominee);
+            continue;
+        };
         match stake_node(params, &nominee, &nominator, &crypto).await {
             Ok(_) => {
                 println!("Staked node: {} by {}", nominee, nominator.address());
@@ -0,0 +1,164 @@
+//! Canonical JSON for signatures, byte for byte what `safeStringify` of
+//! @shardus/crypto-utils produces for the same transaction
+//!
+//! - object keys are sorted the way `Array.prototype.sort` sorts strings, by UTF-16 code units
+//! - `null` stays `null`; `undefined` has no JSON form, a struct models it with
+//!   `#[serde(skip_serializing_if = "Option::is_none")]` and the key is left out as in JS
+//! - numbers are printed the way `JSON.stringify` prints them
+//! - a JS bigint is `{"dataType":"bi","value":"<hex>"}`, the shape of `ShardusBigIntSerialized`
+
+use serde::Serialize;
+use serde_json::Value;
+
+/// The canonical string of a JSON value
+pub fn safe_stringify(value: &Value) -> String {
+    let mut out = String::new();
+    write_value(&mut out, value);
+    out
+}
+
+/// A transaction as JSON without its `sign` field, the value its signature covers
+pub fn unsigned_value<T: Serialize>(tx: &T) -> Result<Value, serde_json::Error> {
+    let mut value = serde_json::to_value(tx)?;
+    if let Some(fields) = value.as_object_mut() {
+        fields.remove("sign");
+    }
+    Ok(value)
+}
+
+fn write_value(out: &mut String, value: &Value) {
+    match value {
+        Value::Null => out.push_str("null"),
+        Value::Bool(b) => out.push_str(if *b { "true" } else { "false" }),
+        Value::Number(n) => write_number(out, n),
+        Value::String(s) => write_string(out, s),
+        Value::Array(items) => {
+            out.push('[');
+            for (i, item) in items.iter().enumerate() {
+                if i > 0 {
+                    out.push(',');
+                }
+                write_value(out, item);
+            }
+            out.push(']');
+        }
+        Value::Object(fields) => {
+            let mut keys: Vec<&String> = fields.keys().collect();
+            keys.sort_by(|a, b| a.encode_utf16().cmp(b.encode_utf16()));
+            out.push('{');
+            for (i, key) in keys.into_iter().enumerate() {
+                if i > 0 {
+                    out.push(',');
+                }
+                write_string(out, key);
+                out.push(':');
+                write_value(out, &fields[key]);
+            }
+            out.push('}');
+        }
+    }
+}
+
+/// `JSON.stringify` of a string: quotes, backslashes and control characters
+/// are escaped, everything else is written as is
+fn write_string(out: &mut String, s: &str) {
+    out.push('"');
+    for c in s.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\u{08}' => out.push_str("\\b"),
+            '\u{0c}' => out.push_str("\\f"),
+            '\n' => out.push_str("\\n"),
+            '\r' => out.push_str("\\r"),
+            '\t' => out.push_str("\\t"),
+            c if (c as u32) < 0x20 => out.push_str(&format!("\\u{:04x}", c as u32)),
+            c => out.push(c),
+        }
+    }
+    out.push('"');
+}
+
+/// `Number.prototype.toString` of a JSON number
+fn write_number(out: &mut String, n: &serde_json::Number) {
+    if n.is_u64() || n.is_i64() {
+        out.push_str(&n.to_string());
+        return;
+    }
+    let Some(f) = n.as_f64() else {
+        // beyond f64 only with serde_json's arbitrary_precision, keep the digits
+        out.push_str(&n.to_string());
+        return;
+    };
+    if !f.is_finite() {
+        out.push_str("null");
+    } else if f == 0.0 {
+        out.push('0');
+    } else if f.abs() >= 1e21 || f.abs() < 1e-6 {
+        // JS switches to exponent notation outside this range and signs the exponent
+        let formatted = format!("{:e}", f);
+        match formatted.split_once('e') {
+            Some((mantissa, exponent)) if !exponent.starts_with('-') => {
+                out.push_str(&format!("{}e+{}", mantissa, exponent))
+            }
+            _ => out.push_str(&formatted),
+        }
+    } else {
+        // shortest round-trip digits without a trailing ".0", as JS prints them
+        out.push_str(&format!("{}", f));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_safe_stringify() {
+        // sorted keys at every level, JS string escapes, null kept
+        assert_eq!(
+            safe_stringify(&json!({
+                "type": "transfer",
+                "amount": { "value": "3635c9adc5dea00000", "dataType": "bi" },
+                "Zeta": null,
+                "chatId": "a\"b\\c\n\u{1}",
+                "timestamp": 1700000000000u64,
+                "list": [1, -2, true, null, "é"],
+            })),
+            r#"{"Zeta":null,"amount":{"dataType":"bi","value":"3635c9adc5dea00000"},"chatId":"a\"b\\c\n\u0001","list":[1,-2,true,null,"é"],"timestamp":1700000000000,"type":"transfer"}"#
+        );
+
+        assert_eq!(safe_stringify(&json!(1.5)), "1.5");
+        assert_eq!(safe_stringify(&json!(100.0)), "100");
+        assert_eq!(safe_stringify(&json!(1e21)), "1e+21");
+        assert_eq!(safe_stringify(&json!(1.5e-7)), "1.5e-7");
+        assert_eq!(safe_stringify(&json!(-0.0)), "0");
+        assert_eq!(safe_stringify(&json!(1e-6)), "0.000001");
+        // astral characters sort by their surrogates, before U+FFFF
+        assert_eq!(
+            safe_stringify(&json!({ "\u{ffff}": 1, "\u{1f600}": 2 })),
+            "{\"\u{1f600}\":2,\"\u{ffff}\":1}"
+        );
+        assert_eq!(safe_stringify(&json!({})), "{}");
+        assert_eq!(safe_stringify(&json!([])), "[]");
+
+        // "sign" is what the signature covers everything else with
+        #[derive(Serialize)]
+        struct Tx {
+            to: String,
+            sign: String,
+            #[serde(skip_serializing_if = "Option::is_none")]
+            memo: Option<String>,
+        }
+        let tx = Tx {
+            to: "ab".to_string(),
+            sign: "0x1c".to_string(),
+            memo: None,
+        };
+        assert_eq!(
+            safe_stringify(&unsigned_value(&tx).unwrap()),
+            r#"{"to":"ab"}"#
+        );
+    }
+}
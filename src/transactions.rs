@@ -1,11 +1,22 @@
-use crate::{cli, crypto, proxy, utils};
-use alloy::signers::{k256::ecdsa::SigningKey, local::LocalSigner, SignerSync};
+use crate::{cli, crypto, economics, proxy, stringify, utils, xmessage};
+use alloy::signers::{
+    k256::ecdsa::{SigningKey, VerifyingKey},
+    local::LocalSigner,
+    SignerSync,
+};
 use serde::{Deserialize, Serialize};
 use std::sync::OnceLock;
 
 static NETWORK_ID: OnceLock<String> = OnceLock::new();
 
-fn get_network_id() -> &'static str {
+/// Override the network id from `.env`, must be called before any transaction is built
+pub fn set_network_id(network_id: &str) -> Result<(), String> {
+    NETWORK_ID
+        .set(network_id.to_string())
+        .map_err(|current| format!("network id is already set to '{}'", current))
+}
+
+pub fn get_network_id() -> &'static str {
     NETWORK_ID.get_or_init(|| {
         dotenvy::dotenv().ok();
         std::env::var("NETWORK_ID")
@@ -22,14 +33,41 @@ pub struct InjectedTxResp {
     pub txId: Option<String>,
 }
 
+#[derive(Debug, Clone)]
 pub enum LiberdusTransactions {
     Register(RegisterTransaction),
     Transfer(TransferTransaction),
     Message(MessageTransaction),
+    Friend(FriendTransaction),
     DepositStake(DepositStakeTransaction),
     ChangeConfig(ChangeConfigTransaction),
 }
 
+impl LiberdusTransactions {
+    pub fn to_json(&self) -> serde_json::Value {
+        match self {
+            LiberdusTransactions::Register(r) => {
+                serde_json::to_value(r).expect("Failed to serialize transaction")
+            }
+            LiberdusTransactions::Transfer(t) => {
+                serde_json::to_value(t).expect("Failed to serialize transaction")
+            }
+            LiberdusTransactions::Message(m) => {
+                serde_json::to_value(m).expect("Failed to serialize transaction")
+            }
+            LiberdusTransactions::Friend(f) => {
+                serde_json::to_value(f).expect("Failed to serialize transaction")
+            }
+            LiberdusTransactions::DepositStake(d) => {
+                serde_json::to_value(d).expect("Failed to serialize transaction")
+            }
+            LiberdusTransactions::ChangeConfig(c) => {
+                serde_json::to_value(c).expect("Failed to serialize transaction")
+            }
+        }
+    }
+}
+
 #[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct ChangeConfigTransaction {
     pub from: String,
@@ -56,7 +94,7 @@ pub struct DepositStakeTransaction {
     pub sign: ShardusSignature,
 }
 
-#[derive(Serialize, Deserialize, Debug, Clone)]
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
 pub struct ShardusSignature {
     owner: String,
     sig: String,
@@ -71,6 +109,10 @@ pub struct RegisterTransaction {
     pub transaction_type: String,
     pub alias: String,
     pub publicKey: String,
+    /// ML-KEM-1024 public key, base64, messages to the account are sealed for.
+    /// Derived from the account's private key by `xmessage::pq_key`, a scheme of
+    /// this tool that clients restoring the account cannot reproduce.
+    pub pqPublicKey: String,
     pub networkId: String,
     pub timestamp: u128,
     pub sign: ShardusSignature,
@@ -114,6 +156,7 @@ pub struct TransferTransaction {
     pub transaction_type: String,
     pub networkId: String,
     pub timestamp: u128,
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub memo: Option<String>,
     #[allow(non_snake_case)]
     pub chatId: String,
@@ -133,7 +176,8 @@ pub struct XMessage {
     #[serde(rename = "encryptionMethod")]
     pub encryption_method: String,
     pub message: String,
-    pub pqSharedKey: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub pqSharedKey: Option<String>,
     #[serde(rename = "senderInfo")]
     pub sender_info: String,
     #[serde(rename = "sent_timestamp")]
@@ -144,7 +188,7 @@ pub fn build_change_config_transaction(
     shardus_crypto: &crypto::ShardusCrypto,
     signer: &LocalSigner<SigningKey>,
     cycle: i64,
-    config: &String,
+    config: &str,
 ) -> ChangeConfigTransaction {
     let from = signer.address().to_string();
     let now = std::time::SystemTime::now()
@@ -152,36 +196,28 @@ pub fn build_change_config_transaction(
         .unwrap()
         .as_millis();
 
-    let tx = serde_json::json!({
-        "from": utils::to_shardus_address(&from),
-        "cycle": cycle,
-        "type": "change_config".to_string(),
-        "config": config,
-        "networkId": get_network_id(),
-        "timestamp": now,
-    });
-
-    let signature =
-        eth_sign_transaction(shardus_crypto, signer, &tx).expect("Failed to sign transaction");
-
-    ChangeConfigTransaction {
+    let mut tx = ChangeConfigTransaction {
         from: utils::to_shardus_address(&from),
         cycle,
-        config: config.clone(),
+        config: config.to_string(),
         networkId: get_network_id().to_string(),
         transaction_type: "change_config".to_string(),
         timestamp: now,
-        sign: signature,
-    }
+        sign: ShardusSignature::default(),
+    };
+    tx.sign = sign_transaction(shardus_crypto, signer, &tx);
+    tx
 }
 
 pub fn build_message_transaction(
     shardus_crypto: &crypto::ShardusCrypto,
     signer: &LocalSigner<SigningKey>,
-    to: &alloy::primitives::Address,
-    message: &String,
+    to_key: &VerifyingKey,
+    to_pq_key: &[u8],
+    message: &str,
 ) -> MessageTransaction {
     let from = signer.address().to_string();
+    let to = alloy::primitives::Address::from_public_key(to_key);
     let now = std::time::SystemTime::now()
         .duration_since(std::time::UNIX_EPOCH)
         .unwrap()
@@ -198,91 +234,55 @@ pub fn build_message_transaction(
             .hash(&addresses.join("").into_bytes(), crypto::Format::Hex)
             .to_string()
     };
-    let XMESSAGE: XMessage = XMessage {
-        encrypted: true,
-        encryption_method: "xchacha20poly1305".to_string(),
-        message: "zknTZuSmFP0clZVqmH2VQS/+R5Ezotgb5L9cJ6WHdy/1iQGPoU1AbqoVJk1gL6GfHc74OlB5/o/n4O8p55DqTRwUMJL73k+GMaXHPlI6Nch3NJg=".to_string(),
-        sender_info: "test_sender_info".to_string(),
-        sent_timestamp: 1,
-        pqSharedKey: "YNHoKakC6Rh72/vM32IumcZfA7hyErmJSeRt2Lhdn7s8ifasv7esuqil5XYXivkOK7bX+CdtfnlP25zgoz0GOs2rJu5E+TIA0qylNRs1PaosvlyV+0DvQJx3kM0rWYk1yaolAJLenjFO43VW+0/XWetzHafZ+YTZ22cCD3K+Y2UK9rXTwOs+cXp2dtnPYlCjEkQDkaONz2vDiLRIb6jSTohV4xd+janPSe9ztsfFFZLueloLm+HiduWgka2atYna/rOY8K1NLZwmiw7ByKBvsQjwCLNZCK36l4V3TKzorae+OlWTr8FyNhdt7AZ+acrUrJc7UY0vXWnscQ+5vevz8wwoItnZpLRbNVpYbfl/ixF7ygs56ZrPu2dr+gG8S72tAdjbbPCLtqou1I5TiJ+RYQdsc7VrjAFq8cjgh9jzMk1N6LVQU7NnbGmvHnKtgL5Ny+YccJciSzNoUBz/sJfzWjoI/zIwhjRHGrAaaK1cLzA73dUNr5UXUZp2QPwydU5tqUmjzsWUVR6dA2dTa+28p+8YnhLFBdw9C/TdnsA2B5pvpXB9TXJdrcq0arrFPcLVhB8lE1EYvn3eJilhi2k8v/ZoQ89Q6/RlsK8vVLuflLtQyEdbauRp4dAQ2vZcefumMxDpLuXkcxziOGv37Y+Y2okKYSV6OO2ct+sv5cHj5RRZpHobn4yYqhmJ/3FF4OXnJIyL0rg9cpm0mUEsyrQBMHRWDvNFwK52Q7s8GgSxyGp/zKhfrtzS8135ICOzdULPAByLwjH4F69KiDgH9ZqjhfU7eeEhEi++7o3oGoejJUg/pxqghqaFY+l4mgAEokV2vZnOEph5q/beQLgDKKTUus5oa/Ww1qilV9OaD0gX36aITG9PLwolX+3Xy8bJnc67zWlakMDmlzfmSowp9JVM8H4kZt/0BYQTn58WkbU7lPmbBXhGFmZ9MGKqhw9zH6srqmwYnOxcrxM8Jo3QVZgFYSTUsWlsnWDfuJI1nd8mZ1Rw6ToZ9YK+cOQG+J0ooBRn+2rPVeDtk8E/7jB+WJILyMfkRAFhuI4sptRgmTSQiISeu3JI/wGoqawGkQo/2/10Rz1naYsk5652GERWwjnD6nVCd0934BGVsrDPh898zIH4zExaLnnusGWcRTXnzkKPHEHNU6xNFL8rJS/GvlcQOQg7lj69V1S0xsOCm4eqUYfNowmFgqQobCERcUWIW3DmWJShOtuhHHt5fadAvmJ/5LKmOQydFLtm/hfgjL/hdzjdk5fm0rRWDNxUOP5Q7x9dSsAB5E+o7j+trsznEzNOHn17gZN0nM4eMKaJT0oDyJz6J0Lw51Wqyp+O2qwJEqnV1188DVh8EBTN5F1aXX3vksMNP5hQxofMTHLtG71FLUfuoYRTPrkFqFnuYr6LDMq42B3vlgu8q4C+QSllxRnByvEyIK6TsQWLwYj2tHfT3gNQnfKuBMwb1lXiBJWTZq0vIxBDd3te6euMbsGiL+PQwS7CMNUCtVAmA/odnDLO0VWtYj8oVgwq272TzNRkEafbM8i45xMiesyd12GOHbSHIJvPveZHx+2aA1A9oiDkyteLzgxQIQX6TUDD9Di1SH2XPzXCMCZz+AF5d0WdO14JY3e0YhWuSB7Nxe5vAuCmVmUwPYiAMYzB3VFqDvxiLgdGy5BMKFV6AiRIbvaYWnxGjPnHozUKcvm4KLtPXrivUYEBoAAffKmwgOpKTfR1jV2jRm2g6dp9z8feE7dsiYRH8NWrhVqA1M78xblGKQDdVZgdoJ8En0290l+ZaBK+BrNUO5qlDvZJrkKaILOw/BwLVbg4Rqr/ZcsQRRGS1lFAx7btREZoi/xgkIgcGzyDxu1MdhA94WFpHgXcARdZUwvsanUfbFF0DwPVlnEzEac6HJ/2DnNSygSoCUmH3hwHyljfz4vuQ7RIQIXb2xxj50y9wVipDeXdU/6UakIOIu4pCoMLHo5BZ/wRpaH+GL+f+C++gR3pAsy1OSgl19BMhbwI2/lchbS1N4r/lpFGrJPIU9Hlw1Qv06fE4wV7biVhAH/lseq5e+maAkOjHbMOXWZxVLDZptdyhaMj0yb0wnJehj0=".to_string(),
-    };
-    let tx = serde_json::json!({
-        "from": utils::to_shardus_address(&from),
-        "amount": serde_json::json!({
-            "dataType": "bi",
-            "value": "0",
-        }),
-        "to": utils::to_shardus_address(&to.to_string()),
-        "type": "message",
-        "chatId": chat_id,
-        "message": message,
-        "networkId": get_network_id(),
-        "timestamp": now,
-        "xmessage": XMESSAGE,
-         "fee": serde_json::json!({
-            "dataType": "bi",
-            "value": "769200000000",
-        }),
-    });
-
-    let signature =
-        eth_sign_transaction(shardus_crypto, signer, &tx).expect("Failed to sign transaction");
 
-    // Manually construct the MessageTransaction struct, just like register transaction does
-    MessageTransaction {
+    // fee and toll as loaded from /netconfig at startup, the toll is what the message pays
+    let economics = economics::current();
+    let mut tx = MessageTransaction {
         from: utils::to_shardus_address(&from),
         to: utils::to_shardus_address(&to.to_string()),
         amount: ShardusBigIntSerialized {
             dataType: "bi".to_string(),
-            value: "0".to_string(),
+            value: format!("{:x}", economics.default_toll),
         },
         transaction_type: "message".to_string(),
         chatId: chat_id,
-        message: message.clone(),
+        message: message.to_string(),
         networkId: get_network_id().to_string(),
         timestamp: now,
-        sign: signature,
-        xmessage: XMESSAGE,
+        sign: ShardusSignature::default(),
+        xmessage: xmessage::seal(signer, to_key, to_pq_key, message, now as u64),
         fee: ShardusBigIntSerialized {
             dataType: "bi".to_string(),
-            value: "769200000000".to_string(),
+            value: format!("{:x}", economics.transaction_fee),
         },
-    }
+    };
+    tx.sign = sign_transaction(shardus_crypto, signer, &tx);
+    tx
 }
 
-#[allow(dead_code)]
 pub fn build_friend_transaction(
     shardus_crypto: &crypto::ShardusCrypto,
     signer: &LocalSigner<SigningKey>,
     to: &alloy::primitives::Address,
-    alias: &String,
+    alias: &str,
 ) -> FriendTransaction {
     let from = signer.address().to_string();
     let now = std::time::SystemTime::now()
         .duration_since(std::time::UNIX_EPOCH)
         .unwrap()
         .as_millis();
-    let tx = serde_json::json!({
-        "from": utils::to_shardus_address(&from),
-        "to": utils::to_shardus_address(&to.to_string()),
-        "type": "friend",
-        "alias": alias,
-        "networkId": get_network_id(),
-        "timestamp": now,
-    });
-
-    let signature =
-        eth_sign_transaction(shardus_crypto, signer, &tx).expect("Failed to sign transaction");
-
-    FriendTransaction {
+
+    let mut tx = FriendTransaction {
         from: utils::to_shardus_address(&from),
         to: utils::to_shardus_address(&to.to_string()),
         transaction_type: "friend".to_string(),
-        alias: alias.clone(),
+        alias: alias.to_string(),
         networkId: get_network_id().to_string(),
         timestamp: now,
-        sign: signature,
-    }
+        sign: ShardusSignature::default(),
+    };
+    tx.sign = sign_transaction(shardus_crypto, signer, &tx);
+    tx
 }
 
 pub fn build_transfer_transaction(
@@ -310,24 +310,7 @@ pub fn build_transfer_transaction(
             .to_string()
     };
 
-    let tx = serde_json::json!({
-        "from": utils::to_shardus_address(&address),
-        "to": utils::to_shardus_address(&to.to_string()),
-        "amount": serde_json::json!({
-            "dataType": "bi",
-            "value": format!("{:x}",amount),
-        }),
-        "memo": "Liberdus Testing Framework Transaction",
-        "chatId": chat_id,
-        "type": "transfer",
-        "networkId": get_network_id(),
-        "timestamp": now,
-    });
-
-    let signature =
-        eth_sign_transaction(shardus_crypto, from, &tx).expect("Failed to sign transaction");
-
-    TransferTransaction {
+    let mut tx = TransferTransaction {
         from: utils::to_shardus_address(&address),
         to: utils::to_shardus_address(&to.to_string()),
         amount: ShardusBigIntSerialized {
@@ -339,14 +322,16 @@ pub fn build_transfer_transaction(
         transaction_type: "transfer".to_string(),
         networkId: get_network_id().to_string(),
         timestamp: now,
-        sign: signature,
-    }
+        sign: ShardusSignature::default(),
+    };
+    tx.sign = sign_transaction(shardus_crypto, from, &tx);
+    tx
 }
 
 pub fn build_deposite_stake_transaction(
     shardus_crypto: &crypto::ShardusCrypto,
     nominator: &LocalSigner<SigningKey>,
-    nominee: &String,
+    nominee: &str,
     amount: u128,
 ) -> DepositStakeTransaction {
     let nominator_address = nominator.address().to_string();
@@ -355,23 +340,8 @@ pub fn build_deposite_stake_transaction(
         .unwrap()
         .as_millis();
 
-    let tx = serde_json::json!({
-        "nominee": nominee,
-        "stake": serde_json::json!({
-            "dataType": "bi",
-            "value": format!("{:x}",amount),
-        }),
-        "nominator": utils::to_shardus_address(&nominator_address),
-        "type": "deposit_stake",
-        "networkId": get_network_id(),
-        "timestamp": now,
-    });
-
-    let signature =
-        eth_sign_transaction(shardus_crypto, nominator, &tx).expect("Failed to sign transaction");
-
-    DepositStakeTransaction {
-        nominee: nominee.clone(),
+    let mut tx = DepositStakeTransaction {
+        nominee: nominee.to_string(),
         stake: ShardusBigIntSerialized {
             dataType: "bi".to_string(),
             value: format!("{:x}", amount),
@@ -380,8 +350,10 @@ pub fn build_deposite_stake_transaction(
         transaction_type: "deposit_stake".to_string(),
         networkId: get_network_id().to_string(),
         timestamp: now,
-        sign: signature,
-    }
+        sign: ShardusSignature::default(),
+    };
+    tx.sign = sign_transaction(shardus_crypto, nominator, &tx);
+    tx
 }
 
 pub fn build_register_transaction(
@@ -404,66 +376,42 @@ pub fn build_register_transaction(
         .to_encoded_point(false)
         .to_string();
 
-    let tx = serde_json::json!({
-        "aliasHash": alias_hash,
-        "from": utils::to_shardus_address(&address),
-        "type": "register",
-        "alias": &alias,
-        "publicKey": uncompressed_public_key,
-        "networkId": get_network_id(),
-        "timestamp": now,
-    });
-
-    let signature =
-        eth_sign_transaction(shardus_crypto, signer, &tx).expect("Failed to sign transaction");
-
-    RegisterTransaction {
+    let mut tx = RegisterTransaction {
         aliasHash: alias_hash,
         from: utils::to_shardus_address(&address),
         transaction_type: "register".to_string(),
         alias: alias.clone(),
         publicKey: uncompressed_public_key,
+        pqPublicKey: sodiumoxide::base64::encode(
+            xmessage::pq_public_key(signer),
+            sodiumoxide::base64::Variant::Original,
+        ),
         networkId: get_network_id().to_string(),
         timestamp: now,
-        sign: signature,
-    }
+        sign: ShardusSignature::default(),
+    };
+    tx.sign = sign_transaction(shardus_crypto, signer, &tx);
+    tx
 }
 
-pub fn eth_sign_transaction(
+/// Sign a transaction struct over the canonical JSON of everything but its `sign` field
+pub fn sign_transaction<T: Serialize>(
     shardus_crypto: &crypto::ShardusCrypto,
     signer: &LocalSigner<SigningKey>,
-    tx: &serde_json::Value,
-) -> Option<ShardusSignature> {
-    let from_address = signer.address().to_string();
-    let message = shardus_crypto
-        .hash(&tx.to_string().into_bytes(), crypto::Format::Hex)
-        .to_string();
-    let signature = signer
-        .sign_message_sync(&message.clone().into_bytes())
-        .expect("Failed to sign message");
-
-    let parity_hex = match signature.v() {
-        false => "1b",
-        true => "1c",
-    };
-
-    match signature.to_k256() {
-        Ok(k) => Some(ShardusSignature {
-            owner: utils::to_shardus_address(&from_address),
-            sig: format!("0x{}{}", k.to_string().to_lowercase(), parity_hex),
-        }),
-        Err(_e) => None,
-    }
+    tx: &T,
+) -> ShardusSignature {
+    let unsigned = stringify::unsigned_value(tx).expect("Failed to serialize transaction");
+    eth_sign_transaction(shardus_crypto, signer, &unsigned).expect("Failed to sign transaction")
 }
 
-pub fn eth_sign_transaction_with_string(
+pub fn eth_sign_transaction(
     shardus_crypto: &crypto::ShardusCrypto,
     signer: &LocalSigner<SigningKey>,
-    tx_json_string: &str,
+    tx: &serde_json::Value,
 ) -> Option<ShardusSignature> {
     let from_address = signer.address().to_string();
     let message = shardus_crypto
-        .hash(&tx_json_string.as_bytes().to_vec(), crypto::Format::Hex)
+        .hash(&stringify::safe_stringify(tx).into_bytes(), crypto::Format::Hex)
         .to_string();
     let signature = signer
         .sign_message_sync(&message.clone().into_bytes())
@@ -483,6 +431,9 @@ pub fn eth_sign_transaction_with_string(
     }
 }
 
+/// Whether `signature` over `tx` was made by `expected_address`, checks what
+/// `eth_sign_transaction` produces
+#[cfg(test)]
 pub fn eth_verify_signature(
     shardus_crypto: &crypto::ShardusCrypto,
     tx: &serde_json::Value,
@@ -493,7 +444,7 @@ pub fn eth_verify_signature(
 
     // Hash the transaction the same way as in eth_sign_transaction
     let message_hash = shardus_crypto
-        .hash(&tx.to_string().into_bytes(), crypto::Format::Hex)
+        .hash(&stringify::safe_stringify(tx).into_bytes(), crypto::Format::Hex)
         .to_string();
 
     // Parse the signature from the ShardusSignature
@@ -543,23 +494,7 @@ pub async fn inject_transaction(
     gateway_url: &String,
     verbosity: &bool,
 ) -> Result<InjectedTxResp, Box<dyn std::error::Error>> {
-    let json_tx = match tx {
-        LiberdusTransactions::Register(r) => {
-            serde_json::to_value(r).expect("Failed to serialize transaction")
-        }
-        LiberdusTransactions::Transfer(t) => {
-            serde_json::to_value(t).expect("Failed to serialize transaction")
-        }
-        LiberdusTransactions::Message(m) => {
-            serde_json::to_value(m).expect("Failed to serialize transaction")
-        }
-        LiberdusTransactions::DepositStake(d) => {
-            serde_json::to_value(d).expect("Failed to serialize transaction")
-        }
-        LiberdusTransactions::ChangeConfig(c) => {
-            serde_json::to_value(c).expect("Failed to serialize transaction")
-        }
-    };
+    let json_tx = tx.to_json();
 
     let (payload, full_url) = {
         let payload = proxy::build_send_transaction_payload(&json_tx);
@@ -661,56 +596,188 @@ mod tests {
         );
         
         let signer = LocalSigner::random();
-        let to_address = alloy::primitives::Address::repeat_byte(0x42);
+        let recipient = LocalSigner::random();
         let message = "test message".to_string();
         
         // Build the transaction
-        let message_tx = build_message_transaction(&shardus_crypto, &signer, &to_address, &message);
-        
-        // Serialize to JSON to see the actual structure
-        let json_output = serde_json::to_string_pretty(&message_tx).unwrap();
-        println!("Message Transaction JSON:\n{}", json_output);
+        let message_tx = build_message_transaction(
+            &shardus_crypto,
+            &signer,
+            recipient.credential().verifying_key(),
+            &xmessage::pq_public_key(&recipient),
+            &message,
+        );
+        assert_eq!(message_tx.to, utils::to_shardus_address(&recipient.address().to_string()));
+        assert_eq!(message_tx.xmessage.sent_timestamp as u128, message_tx.timestamp);
+
+        // only the recipient can read it
+        let (opened, sender_info) = xmessage::open(
+            &recipient,
+            signer.credential().verifying_key(),
+            &message_tx.xmessage,
+        )
+        .unwrap();
+        assert_eq!(opened, message);
+        assert_eq!(
+            sender_info["username"],
+            crate::registration::alias(&signer.address())
+        );
         
-        // Also test what the original tx object looked like before signing
-        let from = signer.address().to_string();
-        let now = std::time::SystemTime::now()
-            .duration_since(std::time::UNIX_EPOCH)
-            .unwrap()
-            .as_millis();
-        let chat_id = {
-            let from_address = utils::to_shardus_address(&from);
-            let to = utils::to_shardus_address(&to_address.to_string());
-            let mut addresses = [from_address, to];
-            addresses.sort();
-            shardus_crypto
-                .hash(&addresses.join("").into_bytes(), crypto::Format::Hex)
-                .to_string()
+        // the signature covers the struct as sent, minus `sign`
+        let unsigned = stringify::unsigned_value(&message_tx).unwrap();
+        assert!(unsigned.get("sign").is_none());
+        assert_eq!(unsigned["xmessage"]["encryptionMethod"], "xchacha20poly1305");
+        assert!(
+            eth_verify_signature(&shardus_crypto, &unsigned, &message_tx.sign, &signer.address())
+                .unwrap()
+        );
+
+        // a field changed after signing breaks it
+        let mut tampered = message_tx.clone();
+        tampered.message = "other message".to_string();
+        let tampered = stringify::unsigned_value(&tampered).unwrap();
+        assert!(
+            !eth_verify_signature(&shardus_crypto, &tampered, &message_tx.sign, &signer.address())
+                .unwrap()
+        );
+    }
+
+    /// Signature vectors checked into `fixtures/`, see `fixtures/README.md`
+    const SIGNATURE_VECTORS: &str = include_str!("../fixtures/signature_vectors.json");
+
+    #[derive(Deserialize)]
+    struct SignatureVector {
+        name: String,
+        private_key: String,
+        inputs: serde_json::Value,
+        tx: serde_json::Value,
+        payload: String,
+        hash: String,
+        sign: ShardusSignature,
+    }
+
+    /// The transaction of `vector` as its builder makes it now, without `sign`
+    fn build_vector(
+        shardus_crypto: &crypto::ShardusCrypto,
+        signer: &LocalSigner<SigningKey>,
+        vector: &SignatureVector,
+    ) -> serde_json::Value {
+        let input = |key: &str| vector.inputs[key].as_str().unwrap_or_default().to_string();
+        let address = |key: &str| input(key).parse::<alloy::primitives::Address>().unwrap();
+        let amount = |key: &str| input(key).parse::<u128>().unwrap();
+        let key = |key: &str| {
+            input(key)
+                .trim_start_matches("0x")
+                .parse::<alloy::signers::local::PrivateKeySigner>()
+                .unwrap()
         };
-        
-        let original_tx = serde_json::json!({
-            "from": utils::to_shardus_address(&from),
-            "amount": serde_json::json!({
-                "dataType": "bi",
-                "value": "0",
-            }),
-            "to": utils::to_shardus_address(&to_address.to_string()),
-            "type": "message",
-            "chatId": chat_id,
-            "message": message,
-            "networkId": get_network_id(),
-            "timestamp": now,
-            "xmessage": {
-                "encrypted": true,
-                "encryptionMethod": "xchacha20poly1305",
-                "message": "test_encrypted_message",
-            },
-            "fee": {
-                "dataType": "bi",
-                "value": "B317E48C00"
-            },
-        });
-        
-        let original_json = serde_json::to_string_pretty(&original_tx).unwrap();
-        println!("Original TX JSON (what gets signed):\n{}", original_json);
+        let unsigned = match vector.name.split(':').next().unwrap() {
+            "register" => stringify::unsigned_value(&build_register_transaction(
+                shardus_crypto,
+                signer,
+                &input("alias"),
+            )),
+            "transfer" => stringify::unsigned_value(&build_transfer_transaction(
+                shardus_crypto,
+                signer,
+                &address("to"),
+                amount("amount"),
+            )),
+            "message" => stringify::unsigned_value(&build_message_transaction(
+                shardus_crypto,
+                signer,
+                key("to_private_key").credential().verifying_key(),
+                &xmessage::pq_public_key(&key("to_private_key")),
+                &input("message"),
+            )),
+            "friend" => stringify::unsigned_value(&build_friend_transaction(
+                shardus_crypto,
+                signer,
+                &address("to"),
+                &input("alias"),
+            )),
+            "deposit_stake" => stringify::unsigned_value(&build_deposite_stake_transaction(
+                shardus_crypto,
+                signer,
+                &input("nominee"),
+                amount("stake"),
+            )),
+            "change_config" => stringify::unsigned_value(&build_change_config_transaction(
+                shardus_crypto,
+                signer,
+                input("cycle").parse().unwrap(),
+                &input("config"),
+            )),
+            other => panic!("no builder for signature vector {}", other),
+        };
+        unsigned.unwrap()
+    }
+
+    #[test]
+    #[ignore = "the corpus is still generated by this crate, record it with `node fixtures/verify_signature_vectors.js --write` and drop this, see fixtures/README.md"]
+    fn test_signature_vectors_from_js() {
+        // vectors this crate made itself only pin it to its own output
+        let corpus: serde_json::Value = serde_json::from_str(SIGNATURE_VECTORS).unwrap();
+        let provenance = &corpus["provenance"];
+        assert!(
+            provenance["source"] == "js" && provenance["verified_against_js"] == true,
+            "signature vectors were not produced by the JS SDK: {}. Run `node fixtures/verify_signature_vectors.js --write`, see fixtures/README.md",
+            provenance
+        );
+    }
+
+    #[test]
+    fn test_signature_vectors() {
+        let corpus: serde_json::Value = serde_json::from_str(SIGNATURE_VECTORS).unwrap();
+        let shardus_crypto = crypto::ShardusCrypto::new(corpus["hash_key"].as_str().unwrap());
+        let vectors: Vec<SignatureVector> =
+            serde_json::from_value(corpus["vectors"].clone()).unwrap();
+        assert!(!vectors.is_empty());
+
+        for vector in &vectors {
+            let signer = vector
+                .private_key
+                .trim_start_matches("0x")
+                .parse::<alloy::signers::local::PrivateKeySigner>()
+                .unwrap();
+
+            // the builder still makes this transaction, up to the clock, the network id
+            // and the random nonces of an encrypted message
+            let mut built = build_vector(&shardus_crypto, &signer, vector);
+            for volatile in ["timestamp", "networkId"] {
+                built[volatile] = vector.tx[volatile].clone();
+            }
+            if built["xmessage"].is_object() {
+                for volatile in ["message", "pqSharedKey", "senderInfo", "sent_timestamp"] {
+                    built["xmessage"][volatile] = vector.tx["xmessage"][volatile].clone();
+                }
+            }
+            assert_eq!(built, vector.tx, "{}: transaction fields", vector.name);
+
+            // a recorded encrypted message still opens for its recipient
+            if let Some(recipient) = vector.inputs["to_private_key"].as_str() {
+                let recipient = recipient
+                    .trim_start_matches("0x")
+                    .parse::<alloy::signers::local::PrivateKeySigner>()
+                    .unwrap();
+                let sealed: XMessage =
+                    serde_json::from_value(vector.tx["xmessage"].clone()).unwrap();
+                let (message, _) =
+                    xmessage::open(&recipient, signer.credential().verifying_key(), &sealed)
+                        .unwrap();
+                assert_eq!(message, vector.inputs["message"], "{}: xmessage", vector.name);
+            }
+
+            // and it still signs to the recorded bytes, hash and signature
+            let payload = stringify::safe_stringify(&vector.tx);
+            assert_eq!(payload, vector.payload, "{}: signed payload", vector.name);
+            let hash = shardus_crypto
+                .hash(&payload.into_bytes(), crypto::Format::Hex)
+                .to_string();
+            assert_eq!(hash, vector.hash, "{}: payload hash", vector.name);
+            let sign = eth_sign_transaction(&shardus_crypto, &signer, &vector.tx).unwrap();
+            assert_eq!(sign.owner, vector.sign.owner, "{}: owner", vector.name);
+            assert_eq!(sign.sig, vector.sign.sig, "{}: signature", vector.name);
+        }
     }
 }
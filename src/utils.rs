@@ -14,13 +14,18 @@ pub fn generate_random_string(length: usize) -> String {
         .collect()
 }
 
+#[derive(Debug, Default, Clone)]
 pub struct InjectionStats {
     pub total: usize,
     pub success: usize,
     pub failed: usize,
+    /// sends dropped because the generator hit its in-flight cap
+    pub skipped: usize,
+    /// sends held back until an in-flight request completed
+    pub late: usize,
 }
 
-pub fn stdout_injection_stats(stats: &InjectionStats, verbosity: &bool) {
+pub fn stdout_injection_stats(stats: &InjectionStats, latency: &str, verbosity: &bool) {
     if *verbosity {
         return;
     }
@@ -29,6 +34,12 @@ pub fn stdout_injection_stats(stats: &InjectionStats, verbosity: &bool) {
         "\rTotal: {:<10} Success: {:<10} Failed: {:<10} Failure: {:<10.2}%",
         stats.total, stats.success, stats.failed, failure_rates
     );
+    if stats.skipped > 0 || stats.late > 0 {
+        print!(" Skipped: {:<10} Late: {:<10}", stats.skipped, stats.late);
+    }
+    if !latency.is_empty() {
+        print!(" {}", latency);
+    }
     std::io::stdout().flush().unwrap();
 }
 
@@ -41,6 +52,15 @@ pub fn stdout_register_progress(max: usize, progress: usize) {
     std::io::stdout().flush().unwrap();
 }
 
+pub fn stdout_confirm_progress(max: usize, confirmed: usize) {
+    let percentage = (confirmed as f64 / max as f64) * 100.0;
+    print!(
+        "\rConfirmed {:?} / {:?} Wallets. ({:<.2}%)",
+        confirmed, max, percentage
+    );
+    std::io::stdout().flush().unwrap();
+}
+
 pub fn append_json_to_file(file_path: &str, json_value: &serde_json::Value) -> std::io::Result<()> {
     let path = std::path::Path::new(file_path);
 
@@ -62,6 +82,29 @@ pub fn append_json_to_file(file_path: &str, json_value: &serde_json::Value) -> s
     Ok(())
 }
 
+pub fn write_json_to_file(file_path: &str, json_value: &serde_json::Value) -> std::io::Result<()> {
+    let path = std::path::Path::new(file_path);
+
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+    std::fs::write(path, serde_json::to_string_pretty(json_value)?)
+}
+
+/// An amount in wei as the gateway reports it
+pub fn parse_wei(value: &serde_json::Value) -> Option<u128> {
+    match value {
+        // shardus serializes bigints as {"dataType": "bi", "value": "<hex>"}
+        serde_json::Value::Object(bigint) => bigint
+            .get("value")
+            .and_then(|v| v.as_str())
+            .and_then(|v| u128::from_str_radix(v.trim_start_matches("0x"), 16).ok()),
+        serde_json::Value::String(decimal) => decimal.parse::<u128>().ok(),
+        serde_json::Value::Number(n) => n.as_u64().map(u128::from),
+        _ => None,
+    }
+}
+
 pub fn to_shardus_address(addr: &String) -> String {
     // cut 0x if it has it
     let mut address = addr.clone();
@@ -0,0 +1,396 @@
+use crate::{
+    crypto::ShardusCrypto,
+    registration,
+    transactions::{self, LiberdusTransactions},
+    utils, xmessage,
+};
+use alloy::{
+    primitives::Address,
+    signers::{k256::ecdsa::VerifyingKey, local::PrivateKeySigner},
+};
+use rand::Rng;
+use std::{fmt, str::FromStr, sync::Arc};
+
+/// Transaction types the load injector knows how to generate
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum TxKind {
+    Transfer,
+    Message,
+    Register,
+    Friend,
+}
+
+impl TxKind {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            TxKind::Transfer => "transfer",
+            TxKind::Message => "message",
+            TxKind::Register => "register",
+            TxKind::Friend => "friend",
+        }
+    }
+
+    /// The builder the injection engine uses for this transaction type
+    pub fn builder(&self) -> Arc<dyn TransactionBuilder> {
+        match self {
+            TxKind::Transfer => Arc::new(TransferBuilder { amount: 1 }),
+            TxKind::Message => Arc::new(MessageBuilder),
+            TxKind::Register => Arc::new(RegisterBuilder),
+            TxKind::Friend => Arc::new(FriendBuilder),
+        }
+    }
+}
+
+impl fmt::Display for TxKind {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.as_str())
+    }
+}
+
+impl FromStr for TxKind {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "transfer" => Ok(TxKind::Transfer),
+            "message" => Ok(TxKind::Message),
+            "register" => Ok(TxKind::Register),
+            "friend" => Ok(TxKind::Friend),
+            _ => Err(format!("'{}' is not a known transaction type", s)),
+        }
+    }
+}
+
+/// Where the injection engine takes the sending account from
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SenderSource {
+    /// A registered wallet from the pool, sending to one of its pre-selected targets
+    Pool,
+    /// A freshly generated key with no target
+    Fresh,
+}
+
+/// The account a transaction goes to, with the keys a message to it is sealed for
+#[derive(Debug, Clone)]
+pub struct Recipient {
+    pub address: Address,
+    pub public_key: VerifyingKey,
+    /// ML-KEM-1024 public key, shared by every sender that has this recipient as a target
+    pub pq_public_key: Arc<Vec<u8>>,
+}
+
+impl Recipient {
+    pub fn of(wallet: &PrivateKeySigner) -> Self {
+        Recipient {
+            address: wallet.address(),
+            public_key: *wallet.credential().verifying_key(),
+            pq_public_key: Arc::new(xmessage::pq_public_key(wallet)),
+        }
+    }
+}
+
+/// Builds one signed transaction for the injection engine.
+///
+/// The engine takes care of scheduling, the wallet pool, stats and logging,
+/// an implementation only turns a sender and a target into a transaction.
+/// `target` is `None` for builders whose senders are `SenderSource::Fresh`.
+/// `build` runs on the blocking pool, signing and sealing may take their time.
+pub trait TransactionBuilder: Send + Sync {
+    fn kind(&self) -> TxKind;
+
+    fn sender_source(&self) -> SenderSource {
+        SenderSource::Pool
+    }
+
+    fn build(
+        &self,
+        shardus_crypto: &ShardusCrypto,
+        sender: &PrivateKeySigner,
+        target: Option<&Recipient>,
+    ) -> LiberdusTransactions;
+}
+
+pub struct TransferBuilder {
+    pub amount: u128,
+}
+
+impl TransactionBuilder for TransferBuilder {
+    fn kind(&self) -> TxKind {
+        TxKind::Transfer
+    }
+
+    fn build(
+        &self,
+        shardus_crypto: &ShardusCrypto,
+        sender: &PrivateKeySigner,
+        target: Option<&Recipient>,
+    ) -> LiberdusTransactions {
+        let target = target.expect("pool senders have a target");
+        LiberdusTransactions::Transfer(transactions::build_transfer_transaction(
+            shardus_crypto,
+            sender,
+            &target.address,
+            self.amount,
+        ))
+    }
+}
+
+pub struct MessageBuilder;
+
+impl TransactionBuilder for MessageBuilder {
+    fn kind(&self) -> TxKind {
+        TxKind::Message
+    }
+
+    fn build(
+        &self,
+        shardus_crypto: &ShardusCrypto,
+        sender: &PrivateKeySigner,
+        target: Option<&Recipient>,
+    ) -> LiberdusTransactions {
+        let target = target.expect("pool senders have a target");
+        let message = utils::generate_random_string(30);
+        LiberdusTransactions::Message(transactions::build_message_transaction(
+            shardus_crypto,
+            sender,
+            &target.public_key,
+            &target.pq_public_key,
+            &message,
+        ))
+    }
+}
+
+pub struct RegisterBuilder;
+
+impl TransactionBuilder for RegisterBuilder {
+    fn kind(&self) -> TxKind {
+        TxKind::Register
+    }
+
+    fn sender_source(&self) -> SenderSource {
+        SenderSource::Fresh
+    }
+
+    fn build(
+        &self,
+        shardus_crypto: &ShardusCrypto,
+        sender: &PrivateKeySigner,
+        _target: Option<&Recipient>,
+    ) -> LiberdusTransactions {
+        LiberdusTransactions::Register(transactions::build_register_transaction(
+            shardus_crypto,
+            sender,
+            &registration::alias(&sender.address()),
+        ))
+    }
+}
+
+/// Adds the target as a friend of the sender. Targets are the sender's pre-selected
+/// ones, so friendships form the same stable graph messages are sent along.
+pub struct FriendBuilder;
+
+impl FriendBuilder {
+    /// Name the sender keeps for a friend, the same every time the friend is added
+    pub fn alias(friend: &Address) -> String {
+        format!("friend{}", hex::encode(&friend[..5]))
+    }
+}
+
+impl TransactionBuilder for FriendBuilder {
+    fn kind(&self) -> TxKind {
+        TxKind::Friend
+    }
+
+    fn build(
+        &self,
+        shardus_crypto: &ShardusCrypto,
+        sender: &PrivateKeySigner,
+        target: Option<&Recipient>,
+    ) -> LiberdusTransactions {
+        let target = target.expect("pool senders have a target");
+        LiberdusTransactions::Friend(transactions::build_friend_transaction(
+            shardus_crypto,
+            sender,
+            &target.address,
+            &FriendBuilder::alias(&target.address),
+        ))
+    }
+}
+
+/// Weighted set of transaction types sharing one wallet pool and one TPS budget,
+/// parsed from `transfer=60,message=35,register=5`
+#[derive(Debug, Clone, PartialEq)]
+pub struct WorkloadMix {
+    entries: Vec<(TxKind, u32)>,
+    total_weight: u32,
+}
+
+impl WorkloadMix {
+    /// A workload made of a single transaction type
+    pub fn single(kind: TxKind) -> Self {
+        WorkloadMix {
+            entries: vec![(kind, 1)],
+            total_weight: 1,
+        }
+    }
+
+    /// Pick a transaction type with probability proportional to its weight
+    pub fn pick<R: Rng>(&self, rng: &mut R) -> TxKind {
+        let mut roll = rng.gen_range(0..self.total_weight);
+        for (kind, weight) in &self.entries {
+            if roll < *weight {
+                return *kind;
+            }
+            roll -= weight;
+        }
+        unreachable!("roll is always below the total weight")
+    }
+
+    pub fn kinds(&self) -> impl Iterator<Item = TxKind> + '_ {
+        self.entries.iter().map(|(kind, _)| *kind)
+    }
+
+    pub fn is_mixed(&self) -> bool {
+        self.entries.len() > 1
+    }
+}
+
+impl FromStr for WorkloadMix {
+    type Err = String;
+
+    fn from_str(spec: &str) -> Result<Self, Self::Err> {
+        let mut entries: Vec<(TxKind, u32)> = Vec::new();
+
+        for part in spec.split(',').map(str::trim).filter(|p| !p.is_empty()) {
+            let (kind, weight) = part
+                .split_once('=')
+                .ok_or_else(|| format!("'{}' must be in the form type=weight", part))?;
+            let kind = kind.trim().parse::<TxKind>()?;
+            let weight = weight
+                .trim()
+                .parse::<u32>()
+                .map_err(|_| format!("'{}' is not a valid weight", weight))?;
+
+            if entries.iter().any(|(k, _)| *k == kind) {
+                return Err(format!("'{}' appears more than once in the mix", kind));
+            }
+            if weight > 0 {
+                entries.push((kind, weight));
+            }
+        }
+
+        let total_weight = entries
+            .iter()
+            .try_fold(0u32, |total, (_, weight)| total.checked_add(*weight))
+            .ok_or_else(|| format!("weights of the mix add up to more than {}", u32::MAX))?;
+        if total_weight == 0 {
+            return Err(
+                "mix needs at least one transaction type with a positive weight".to_string(),
+            );
+        }
+
+        Ok(WorkloadMix {
+            entries,
+            total_weight,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::{rngs::StdRng, SeedableRng};
+
+    #[test]
+    fn test_parse_mix() {
+        let mix = "transfer=60,message=35,register=5"
+            .parse::<WorkloadMix>()
+            .unwrap();
+        assert_eq!(
+            mix.kinds().collect::<Vec<_>>(),
+            vec![TxKind::Transfer, TxKind::Message, TxKind::Register]
+        );
+        assert_eq!(mix.total_weight, 100);
+
+        let mix = "message=1, transfer=0".parse::<WorkloadMix>().unwrap();
+        assert_eq!(mix.kinds().collect::<Vec<_>>(), vec![TxKind::Message]);
+
+        let mix = "friend=10,message=90".parse::<WorkloadMix>().unwrap();
+        assert_eq!(
+            mix.kinds().collect::<Vec<_>>(),
+            vec![TxKind::Friend, TxKind::Message]
+        );
+
+        assert!("transfer".parse::<WorkloadMix>().is_err());
+        assert!("transfer=x".parse::<WorkloadMix>().is_err());
+        assert!("stake=1".parse::<WorkloadMix>().is_err());
+        assert!("transfer=0".parse::<WorkloadMix>().is_err());
+        assert!("transfer=1,transfer=2".parse::<WorkloadMix>().is_err());
+        assert!("transfer=4294967295,message=1"
+            .parse::<WorkloadMix>()
+            .is_err());
+    }
+
+    #[test]
+    fn test_friend_alias() {
+        assert_eq!(
+            FriendBuilder::alias(&Address::repeat_byte(0xab)),
+            "friendababababab"
+        );
+        // same friend, same alias, so adding a friend again does not rename it
+        let friend = PrivateKeySigner::random().address();
+        assert_eq!(FriendBuilder::alias(&friend), FriendBuilder::alias(&friend));
+    }
+
+    #[test]
+    fn test_friend_build() {
+        let shardus_crypto =
+            ShardusCrypto::new("69fa4195670576c0160d660c3be36556ff8d504725be8a59b5a96509e0c994bc");
+        let sender = PrivateKeySigner::random();
+        let friend = PrivateKeySigner::random();
+
+        let LiberdusTransactions::Friend(tx) =
+            FriendBuilder.build(&shardus_crypto, &sender, Some(&Recipient::of(&friend)))
+        else {
+            panic!("friend builder built another transaction type");
+        };
+        assert_eq!(
+            tx.from,
+            utils::to_shardus_address(&sender.address().to_string())
+        );
+        assert_eq!(
+            tx.to,
+            utils::to_shardus_address(&friend.address().to_string())
+        );
+        assert_eq!(tx.transaction_type, "friend");
+        assert_eq!(tx.alias, FriendBuilder::alias(&friend.address()));
+        assert_eq!(tx.networkId, transactions::get_network_id());
+
+        let unsigned = crate::stringify::unsigned_value(&tx).unwrap();
+        assert!(transactions::eth_verify_signature(
+            &shardus_crypto,
+            &unsigned,
+            &tx.sign,
+            &sender.address()
+        )
+        .unwrap());
+        assert!(!transactions::eth_verify_signature(
+            &shardus_crypto,
+            &unsigned,
+            &tx.sign,
+            &friend.address()
+        )
+        .unwrap());
+    }
+
+    #[test]
+    fn test_pick_follows_weights() {
+        let mix = "transfer=75,message=25".parse::<WorkloadMix>().unwrap();
+        let mut rng = StdRng::seed_from_u64(7);
+
+        let transfers = (0..10_000)
+            .filter(|_| mix.pick(&mut rng) == TxKind::Transfer)
+            .count();
+        assert!((7_000..8_000).contains(&transfers));
+    }
+}